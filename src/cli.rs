@@ -0,0 +1,126 @@
+use std::fmt;
+
+pub const USAGE: &str = "\
+usage: crabtracer [options] <scene.json>
+
+options:
+  -o, --output <file>          output image path (default: output_image.png)
+  -s, --samples <n>            override image_samples from the scene
+  -r, --resolution <w>x<h>     override the camera resolution
+  -t, --threads <n>            number of render threads, 0 for all cores
+  -i, --integrator <type>      override the integrator: recursive, path, ao,
+                               normals, depth, uv or id
+      --seed <n>               seed for reproducible renders, random if not given
+  -v, --verbose                print acceleration structure statistics
+  -h, --help                   print this message";
+
+// options given on the command line
+// values left as None keep the setting from the scene file
+pub struct Options {
+    pub scene_path: String,
+    pub output_path: String,
+    pub samples: Option<i64>,
+    pub resolution: Option<(i64, i64)>,
+    pub threads: Option<i64>,
+    pub seed: Option<u64>,
+    pub integrator: Option<String>,
+    pub verbose: bool,
+    pub help: bool
+}
+
+pub struct ArgError(pub String);
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// get the value following an option
+fn option_value<'a, I: Iterator<Item = &'a String>>(args: &mut I, name: &str) -> Result<&'a String, ArgError> {
+    match args.next() {
+        Some(v) => Ok(v),
+        None => Err(ArgError(format!("missing value for {}", name)))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(v: &str, name: &str) -> Result<T, ArgError> {
+    match v.parse::<T>() {
+        Ok(x) => Ok(x),
+        Err(_) => Err(ArgError(format!("invalid value \"{}\" for {}", v, name)))
+    }
+}
+
+// parse arguments, not including the program name
+pub fn parse_args(args: &[String]) -> Result<Options, ArgError> {
+    let mut options = Options {
+        scene_path: String::new(),
+        output_path: String::from("output_image.png"),
+        samples: None,
+        resolution: None,
+        threads: None,
+        seed: None,
+        integrator: None,
+        verbose: false,
+        help: false
+    };
+    let mut scene_path: Option<String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "-o" | "--output" => options.output_path = option_value(&mut iter, arg)?.clone(),
+            "-s" | "--samples" => {
+                let n: i64 = parse_number(option_value(&mut iter, arg)?, arg)?;
+                if n < 1 {
+                    return Err(ArgError(format!("{} must be at least 1", arg)));
+                }
+                options.samples = Some(n);
+            },
+            "-r" | "--resolution" => {
+                let v = option_value(&mut iter, arg)?;
+                let (w, h) = match v.split_once('x') {
+                    Some(x) => x,
+                    None => return Err(ArgError(format!("{} expects <width>x<height>, got \"{}\"", arg, v)))
+                };
+                let w: i64 = parse_number(w, arg)?;
+                let h: i64 = parse_number(h, arg)?;
+                if w < 1 || h < 1 {
+                    return Err(ArgError(format!("{} must be at least 1x1", arg)));
+                }
+                options.resolution = Some((w, h));
+            },
+            "-t" | "--threads" => {
+                let n: i64 = parse_number(option_value(&mut iter, arg)?, arg)?;
+                if n < 0 {
+                    return Err(ArgError(format!("{} can not be negative", arg)));
+                }
+                options.threads = Some(n);
+            },
+            "-i" | "--integrator" => options.integrator = Some(option_value(&mut iter, arg)?.clone()),
+            "--seed" => options.seed = Some(parse_number(option_value(&mut iter, arg)?, arg)?),
+            _ => {
+                if arg.starts_with('-') {
+                    return Err(ArgError(format!("unknown option {}", arg)));
+                }
+                if scene_path.is_some() {
+                    return Err(ArgError(format!("unexpected argument \"{}\"", arg)));
+                }
+                scene_path = Some(arg.clone());
+            }
+        }
+    }
+
+    if options.help {
+        return Ok(options);
+    }
+
+    options.scene_path = match scene_path {
+        Some(p) => p,
+        None => return Err(ArgError(String::from("no scene file given")))
+    };
+
+    Ok(options)
+}
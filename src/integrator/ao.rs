@@ -0,0 +1,53 @@
+use crate::integrator::Integrator;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::SurfaceBase;
+use crate::util::*;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// fraction of cosine weighted rays around the first hit that escape within
+// max_distance, white on a miss
+pub struct AmbientOcclusion {
+    max_distance: f64,
+    samples: i64
+}
+
+impl Integrator for AmbientOcclusion {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        Ok(AmbientOcclusion {
+            max_distance: safe_value_to_f64(&j["max_distance"], f64::INFINITY),
+            samples: safe_value_to_i64(&j["samples"], 16).max(1)
+        })
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        let mut hit = HitInfo::new();
+        if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        // occlusion is measured on the side facing the camera
+        let n = if hit.sn.dot(ray.d) > 0.0 { -hit.sn } else { hit.sn };
+
+        let mut unoccluded = 0;
+        for _ in 0..self.samples {
+            let rv = sampler.next_2d();
+            let d = n + random_on_unit_sphere(rv.x, rv.y);
+            if d.length_squared() < 1e-12 {
+                continue;
+            }
+
+            let mut ao_ray = Ray::new(hit.p, d.normalize(), None, Some(self.max_distance));
+            let mut ao_hit = HitInfo::new();
+            if !scene.m_surface_group.intersect(&mut ao_ray, &mut ao_hit) {
+                unoccluded += 1;
+            }
+        }
+
+        let v = unoccluded as f64 / self.samples as f64;
+        Color::new(v, v, v)
+    }
+}
@@ -0,0 +1,38 @@
+use crate::integrator::Integrator;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::SurfaceBase;
+use crate::util::*;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// distance to the first hit mapped from [near, far] to [0, 1], white on a miss
+pub struct Depth {
+    near: f64,
+    far: f64
+}
+
+impl Integrator for Depth {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let near = safe_value_to_f64(&j["near"], 0.0);
+        let far = safe_value_to_f64(&j["far"], 10.0);
+        if far <= near {
+            return Err(format!("depth far ({}) must be greater than near ({})", far, near));
+        }
+        Ok(Depth { near, far })
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, _sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        let mut hit = HitInfo::new();
+        if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        // camera rays are not normalized, t is scaled to a distance
+        let distance = hit.t * ray.d.length();
+        let v = ((distance - self.near) / (self.far - self.near)).clamp(0.0, 1.0);
+        Color::new(v, v, v)
+    }
+}
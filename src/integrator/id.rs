@@ -0,0 +1,49 @@
+use crate::integrator::Integrator;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::SurfaceBase;
+use crate::util::*;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+enum IdSource {
+    OBJECT,     // index of the surface entry in the scene file
+    MATERIAL    // material type
+}
+
+// false color per object or material id at the first hit, black on a miss
+pub struct Id {
+    source: IdSource
+}
+
+// bright pseudo random color for an id, stable between renders
+fn id_color(id: u32) -> Color {
+    let h = hash_u64(id as u64);
+    let channel = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+impl Integrator for Id {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let source = match j["id"].as_str().unwrap_or("object") {
+            "object" => IdSource::OBJECT,
+            "material" => IdSource::MATERIAL,
+            v => return Err(format!("unknown id source \"{}\"", v))
+        };
+        Ok(Id { source })
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, _sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        let mut hit = HitInfo::new();
+        if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+            return Color::BLACK;
+        }
+
+        match self.source {
+            IdSource::OBJECT => id_color(hit.object_id),
+            IdSource::MATERIAL => id_color(hit.mat.kind_id())
+        }
+    }
+}
@@ -0,0 +1,77 @@
+mod recursive;
+mod normals;
+mod path;
+mod ao;
+mod depth;
+mod uv;
+mod id;
+
+use recursive::Recursive;
+use normals::Normals;
+use path::PathTracer;
+use ao::AmbientOcclusion;
+use depth::Depth;
+use uv::Uv;
+use id::Id;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::util::color::Color;
+use crate::util::ray::Ray;
+use serde_json::{Value};
+
+pub enum ObjectIntegrator {
+    RECURSIVE(Recursive),
+    NORMALS(Normals),
+    PATH(PathTracer),
+    AO(AmbientOcclusion),
+    DEPTH(Depth),
+    UV(Uv),
+    ID(Id)
+}
+
+pub trait Integrator {
+    // radiance arriving at the camera along ray
+    fn li(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Color;
+
+    fn from_json(j: &Value) -> Result<Self, String> where Self: Sized;
+}
+
+impl Integrator for ObjectIntegrator {
+    // missing "integrator" block or type keeps the recursive tracer
+    fn from_json(j: &Value) -> Result<Self, String> {
+        if !j.is_null() && !j.is_object() {
+            return Err(format!("integrator must be an object, got {}", j));
+        }
+        let integrator_type = match j.get("type") {
+            Some(v) => match v.as_str() {
+                Some(t) => t,
+                None => return Err(format!("integrator type must be a string, got {}", v))
+            },
+            None => "recursive"
+        };
+
+        let integrator = match integrator_type {
+            "recursive" => ObjectIntegrator::RECURSIVE(Recursive::from_json(j)?),
+            "normals" => ObjectIntegrator::NORMALS(Normals::from_json(j)?),
+            "path" | "path_mis" => ObjectIntegrator::PATH(PathTracer::from_json(j)?),
+            "ao" | "ambient_occlusion" => ObjectIntegrator::AO(AmbientOcclusion::from_json(j)?),
+            "depth" => ObjectIntegrator::DEPTH(Depth::from_json(j)?),
+            "uv" => ObjectIntegrator::UV(Uv::from_json(j)?),
+            "id" => ObjectIntegrator::ID(Id::from_json(j)?),
+            _ => return Err(format!("could not parse integrator \"{}\"", integrator_type))
+        };
+        Ok(integrator)
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Color {
+        match self {
+            ObjectIntegrator::RECURSIVE(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::NORMALS(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::PATH(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::AO(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::DEPTH(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::UV(v) => v.li(scene, ray, sampler),
+            ObjectIntegrator::ID(v) => v.li(scene, ray, sampler)
+        }
+    }
+}
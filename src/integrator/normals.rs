@@ -0,0 +1,27 @@
+use crate::integrator::Integrator;
+use crate::scene::Scene;
+use crate::surfaces::SurfaceBase;
+use crate::sampler::Sampler;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// shading normal at the first hit mapped from [-1, 1] to [0, 1], black on a miss
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn from_json(_j: &Value) -> Result<Self, String> {
+        Ok(Normals)
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, _sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        let mut hit = HitInfo::new();
+        if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+            return Color::BLACK;
+        }
+
+        let n = (hit.sn + 1.0) * 0.5;
+        Color::new(n.x, n.y, n.z)
+    }
+}
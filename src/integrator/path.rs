@@ -0,0 +1,134 @@
+use crate::integrator::Integrator;
+use crate::material::{Material, ScatterRecord};
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::{SurfaceBase, EmitterRecord};
+use crate::util::*;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// weighting of light and bsdf samples
+#[derive(Clone, Copy)]
+enum MisHeuristic {
+    BALANCE,
+    POWER
+}
+
+impl MisHeuristic {
+    fn from_name(name: &str) -> Result<MisHeuristic, String> {
+        match name {
+            "balance" => Ok(MisHeuristic::BALANCE),
+            "power" => Ok(MisHeuristic::POWER),
+            _ => Err(format!("unknown mis heuristic \"{}\"", name))
+        }
+    }
+
+    // weight of a sample drawn with density pdf_a when pdf_b could also have produced it
+    fn weight(&self, pdf_a: f64, pdf_b: f64) -> f64 {
+        let (a, b) = match self {
+            MisHeuristic::BALANCE => (pdf_a, pdf_b),
+            MisHeuristic::POWER => (pdf_a * pdf_a, pdf_b * pdf_b)
+        };
+        if a + b == 0.0 {
+            return 0.0;
+        }
+        a / (a + b)
+    }
+}
+
+// iterative path tracer with next event estimation, at every non-specular
+// vertex one emitter sample and the bsdf sample used to continue the path
+// both count emission with mis weights
+pub struct PathTracer {
+    heuristic: MisHeuristic
+}
+
+impl PathTracer {
+    // light from one sampled emitter scattered by a non-specular surface,
+    // weighted against the bsdf sampling strategy
+    fn sample_light(&self, scene: &Scene, ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = EmitterRecord::new(hit.p);
+        let radiance = scene.m_surface_group.sample_light(&mut rec, sampler.next_2d());
+        if rec.pdf == 0.0 {
+            return Color::BLACK;
+        }
+
+        let f = hit.mat.as_ref().eval(ray, hit, rec.wi);
+        if f == Color::BLACK {
+            return Color::BLACK;
+        }
+
+        let mut shadow_ray = Ray::new(hit.p, rec.wi, None, Some(rec.hit.t - EPSILON));
+        let mut shadow_hit = HitInfo::new();
+        if scene.m_surface_group.intersect(&mut shadow_ray, &mut shadow_hit) {
+            return Color::BLACK;
+        }
+
+        let bsdf_pdf = hit.mat.as_ref().pdf(ray, hit, rec.wi);
+        return f * radiance * self.heuristic.weight(rec.pdf, bsdf_pdf);
+    }
+}
+
+impl Integrator for PathTracer {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        Ok(PathTracer {
+            heuristic: MisHeuristic::from_name(j["heuristic"].as_str().unwrap_or("power"))?
+        })
+    }
+
+    fn li(&self, scene: &Scene, camera_ray: &Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(camera_ray.o, camera_ray.d.normalize(), None, None);
+        let mut radiance = Color::BLACK;
+        let mut beta = Color::new(1.0, 1.0, 1.0);
+
+        // emission seen by the camera or through a specular bounce is not
+        // reachable by light sampling and counts fully
+        let mut specular = true;
+        let mut bsdf_pdf = 0.0;
+        let has_lights = scene.m_surface_group.light_count() > 0;
+
+        for depth in 0..MAX_RAYTRACE_DEPTH {
+            let mut hit = HitInfo::new();
+            if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+                radiance += beta * scene.m_background;
+                break;
+            }
+
+            let emitted = hit.mat.as_ref().emitted(&ray, &hit);
+            if emitted != Color::BLACK {
+                if specular {
+                    radiance += beta * emitted;
+                } else {
+                    let light_pdf = scene.m_surface_group.light_pdf(&ray, &hit);
+                    radiance += beta * emitted * self.heuristic.weight(bsdf_pdf, light_pdf);
+                }
+            }
+
+            let mut srec = ScatterRecord::new();
+            if !hit.mat.as_ref().sample(&ray, &hit, sampler, &mut srec) {
+                break;
+            }
+
+            if !srec.is_specular && has_lights {
+                radiance += beta * self.sample_light(scene, &ray, &hit, sampler);
+            }
+
+            bsdf_pdf = if srec.is_specular { 0.0 } else { hit.mat.as_ref().pdf(&ray, &hit, srec.wo) };
+            specular = srec.is_specular;
+            beta *= srec.attenuation;
+            ray = Ray::new(hit.p, srec.wo, None, None);
+
+            // russian roulette once the path has had a few bounces
+            if depth >= 3 {
+                let survive = beta.max_component().min(0.95);
+                if sampler.next_1d() >= survive {
+                    break;
+                }
+                beta = beta / survive;
+            }
+        }
+
+        return radiance;
+    }
+}
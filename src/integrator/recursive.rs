@@ -0,0 +1,90 @@
+use crate::integrator::Integrator;
+use crate::material::{Material, ObjectMaterial, Lambertian, ScatterRecord};
+use crate::material::texture::Texture;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::{SurfaceBase, EmitterRecord};
+use crate::util::*;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// recursive tracer following one scattered ray per bounce, with next event
+// estimation at lambertian surfaces
+pub struct Recursive;
+
+impl Recursive {
+    // direct light arriving at a lambertian surface from one sampled emitter
+    fn sample_direct(&self, scene: &Scene, hit: &HitInfo, material: &Lambertian, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = EmitterRecord::new(hit.p);
+        let radiance = scene.m_surface_group.sample_light(&mut rec, sampler.next_2d());
+        if rec.pdf == 0.0 {
+            return Color::BLACK;
+        }
+
+        let cos_theta = hit.sn.dot(rec.wi);
+        if cos_theta <= 0.0 {
+            return Color::BLACK;
+        }
+
+        // shadow ray stops just short of the sampled point
+        let mut shadow_ray = Ray::new(hit.p, rec.wi, None, Some(rec.hit.t - EPSILON));
+        let mut shadow_hit = HitInfo::new();
+        if scene.m_surface_group.intersect(&mut shadow_ray, &mut shadow_hit) {
+            return Color::BLACK;
+        }
+
+        return material.albedo.value(hit) * radiance * (cos_theta / M_PI);
+    }
+
+    // count_emitted is false when the previous bounce already sampled emitters directly
+    fn ray_trace_color(&self, scene: &Scene, ray: &mut Ray, depth: i32, count_emitted: bool, sampler: &mut dyn Sampler) -> Color {
+        let mut hit = HitInfo::new();
+
+        if scene.m_surface_group.intersect(ray, &mut hit) {
+            // get emitted color
+            let emitted = if count_emitted {
+                hit.mat.as_ref().emitted(ray, &hit)
+            } else {
+                Color::BLACK
+            };
+
+            if depth < MAX_RAYTRACE_DEPTH {
+                // next event estimation at diffuse surfaces, the emitters found
+                // by the scattered ray are then skipped to avoid counting them twice
+                let mut direct = Color::BLACK;
+                let mut next_count_emitted = true;
+                if let ObjectMaterial::LAMBERTIAN(l) = hit.mat.as_ref() {
+                    if scene.m_surface_group.light_count() > 0 {
+                        direct = self.sample_direct(scene, &hit, l, sampler);
+                        next_count_emitted = false;
+                    }
+                }
+
+                // get scattered ray
+                let mut srec = ScatterRecord::new();
+                if !hit.mat.as_ref().sample(ray, &hit, sampler, &mut srec) {
+                    return emitted + direct;
+                }
+                let mut scattered = Ray::new(hit.p, srec.wo, None, None);
+
+                // call recursivley
+                return emitted + direct + srec.attenuation * self.ray_trace_color(scene, &mut scattered, depth + 1, next_count_emitted, sampler);
+            }
+            return emitted;
+        } else {
+            return scene.m_background;
+        }
+    }
+}
+
+impl Integrator for Recursive {
+    fn from_json(_j: &Value) -> Result<Self, String> {
+        Ok(Recursive)
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        self.ray_trace_color(scene, &mut ray, 0, true, sampler)
+    }
+}
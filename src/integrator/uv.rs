@@ -0,0 +1,27 @@
+use crate::integrator::Integrator;
+use crate::scene::Scene;
+use crate::sampler::Sampler;
+use crate::surfaces::SurfaceBase;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use serde_json::{Value};
+
+// texture coordinates at the first hit as red and green, black on a miss
+pub struct Uv;
+
+impl Integrator for Uv {
+    fn from_json(_j: &Value) -> Result<Self, String> {
+        Ok(Uv)
+    }
+
+    fn li(&self, scene: &Scene, ray: &Ray, _sampler: &mut dyn Sampler) -> Color {
+        let mut ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+        let mut hit = HitInfo::new();
+        if !scene.m_surface_group.intersect(&mut ray, &mut hit) {
+            return Color::BLACK;
+        }
+
+        // wrap coordinates outside the unit square so tiling stays visible
+        Color::new(hit.uv.x.rem_euclid(1.0), hit.uv.y.rem_euclid(1.0), 0.0)
+    }
+}
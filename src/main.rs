@@ -1,20 +1,111 @@
+#![allow(clippy::needless_return, clippy::upper_case_acronyms)]
+
 use std::fs::File;
 use std::io::BufReader;
-use serde_json::{Value};
+use std::process::ExitCode;
+use serde_json::{Map, Value, json};
 use crate::scene::Scene;
+use crate::cli::Options;
 
 mod scene;
 mod util;
 mod surfaces;
 mod material;
+mod cli;
+mod output;
+mod integrator;
+mod sampler;
+
+// exit codes
+const EXIT_ERROR: u8 = 1;   // scene could not be read or image could not be written
+const EXIT_USAGE: u8 = 2;   // invalid command line
+
+// json object under key in the scene description, created if missing
+fn scene_block<'a>(scene_json: &'a mut Value, key: &str) -> Result<&'a mut Map<String, Value>, String> {
+    let block = &mut scene_json[key];
+    if block.is_null() {
+        *block = json!({});
+    }
+    block.as_object_mut().ok_or_else(|| format!("\"{}\" must be an object", key))
+}
 
-fn main() {
-    let scene_file = File::open("./scenes/plane.json").expect("Error opening scene file");
+// apply command line overrides to the scene description
+fn apply_overrides(scene_json: &mut Value, options: &Options) -> Result<(), String> {
+    if let Some(samples) = options.samples {
+        scene_json["image_samples"] = json!(samples);
+    }
+    if let Some((w, h)) = options.resolution {
+        scene_block(scene_json, "camera")?.insert(String::from("resolution"), json!([w, h]));
+    }
+    if let Some(threads) = options.threads {
+        scene_json["threads"] = json!(threads);
+    }
+    if let Some(seed) = options.seed {
+        scene_json["seed"] = json!(seed);
+    }
+    // settings of the scene integrator block are kept
+    if let Some(integrator) = &options.integrator {
+        scene_block(scene_json, "integrator")?.insert(String::from("type"), json!(integrator));
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let scene_file = File::open(&options.scene_path)
+        .map_err(|e| format!("could not open scene file \"{}\": {}", options.scene_path, e))?;
     let reader = BufReader::new(scene_file);
-    let scene_json: Value = serde_json::from_reader(reader).expect("Error reading scene file");
+    let mut scene_json: Value = serde_json::from_reader(reader)
+        .map_err(|e| format!("could not parse scene file \"{}\": {}", options.scene_path, e))?;
+
+    if !scene_json.is_object() {
+        return Err(format!("scene file \"{}\" does not contain a json object", options.scene_path));
+    }
+    apply_overrides(&mut scene_json, options)
+        .map_err(|e| format!("could not load scene \"{}\": {}", options.scene_path, e))?;
 
-    let scene_m = Scene::parse_from_json(&scene_json);
+    // unseeded renders get a random seed, reported so they can be reproduced
+    if scene_json.get("seed").is_none() {
+        let seed: u64 = rand::random();
+        scene_json["seed"] = json!(seed);
+        eprintln!("seed: {}", seed);
+    }
+
+    let scene_m = Scene::parse_from_json(&scene_json)
+        .map_err(|e| format!("could not load scene \"{}\": {}", options.scene_path, e))?;
+    if options.verbose {
+        if let Some(stats) = &scene_m.m_surface_group.m_bvh_stats {
+            eprintln!("{}", stats);
+        }
+    }
 
     let image = scene_m.ray_trace_image();
-    image.save("output_image.png").unwrap();
+    output::save_image(&options.output_path, &image, &scene_m.m_tonemapper, &scene_m.m_output)
+        .map_err(|e| format!("could not write image \"{}\": {}", options.output_path, e))?;
+
+    return Ok(());
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let options = match cli::parse_args(&args) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
 }
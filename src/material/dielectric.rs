@@ -0,0 +1,101 @@
+use crate::material::{Material, ScatterRecord};
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use crate::util::*;
+use serde_json::{Value};
+use crate::sampler::Sampler;
+
+// approximation used for the reflected fraction of light
+#[derive(Clone, Copy, PartialEq)]
+pub enum FresnelMode {
+    EXACT,
+    SCHLICK
+}
+
+// smooth transparent material such as glass or water
+pub struct Dielectric {
+    pub ior: f64,       // index of refraction of the inside, outside is vacuum
+    pub tint: Color,    // attenuation applied to reflected and refracted rays
+    pub fresnel: FresnelMode
+}
+
+impl Dielectric {
+    // fraction of light reflected at an interface
+    // cos_i is the cosine of the incident angle, eta the ratio incident over transmitted index
+    pub fn reflectance(&self, cos_i: f64, eta: f64) -> f64 {
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t >= 1.0 {
+            return 1.0;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+
+        match self.fresnel {
+            FresnelMode::EXACT => {
+                let r_s = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+                let r_p = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+                0.5 * (r_s * r_s + r_p * r_p)
+            },
+            FresnelMode::SCHLICK => {
+                let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+                // use the angle on the optically thinner side
+                let c = if eta > 1.0 { cos_t } else { cos_i };
+                r0 + (1.0 - r0) * (1.0 - c).powi(5)
+            }
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let ior = match j.get("ior") {
+            Some(v) => safe_value_to_f64(v, 1.5),
+            None => 1.5
+        };
+
+        let fresnel = match j.get("fresnel").and_then(|v| v.as_str()) {
+            Some("exact") | None => FresnelMode::EXACT,
+            Some("schlick") => FresnelMode::SCHLICK,
+            Some(other) => return Err(format!("unknown fresnel mode \"{}\"", other))
+        };
+
+        Ok(Dielectric {
+            ior,
+            tint: Color::from_json(&j["tint"], Color::new(1.0, 1.0, 1.0))?,
+            fresnel
+        })
+    }
+
+    fn emitted(&self, _ray: &Ray, _hit: &HitInfo) -> Color {
+        Color::BLACK
+    }
+
+    fn sample(&self, ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler, srec: &mut ScatterRecord) -> bool {
+        let unit_d = ray.d.normalize();
+
+        // orient normal against the ray, entering or leaving the medium
+        let (n, eta) = if hit.front_face {
+            (hit.sn, 1.0 / self.ior)
+        } else {
+            (-hit.sn, self.ior)
+        };
+        let cos_i = (-unit_d.dot(n)).clamp(0.0, 1.0);
+
+        // choose reflection or refraction proportional to the fresnel term,
+        // the sample is drawn even under total internal reflection so every
+        // path uses the same number of sampler dimensions per bounce
+        let f = self.reflectance(cos_i, eta);
+        let u = sampler.next_1d();
+        srec.wo = match refract(unit_d, n, eta) {
+            Some(t) if u >= f => t.normalize(),
+            _ => reflect(unit_d, n)
+        };
+        srec.attenuation = self.tint;
+        srec.is_specular = true;
+
+        return true;
+    }
+
+    fn albedo(&self, _hit: &HitInfo) -> Color {
+        self.tint
+    }
+}
@@ -0,0 +1,45 @@
+use crate::material::{Material, ScatterRecord};
+use crate::material::texture::{ObjectTexture, Texture};
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use crate::util::*;
+use serde_json::{Value};
+use crate::sampler::Sampler;
+
+// area light emitting uniformly in all directions over the hemisphere
+pub struct DiffuseLight {
+    pub emission: ObjectTexture,
+    pub intensity: f64,     // multiplier on the emission color
+    pub two_sided: bool     // emit from the back face as well
+}
+
+impl Material for DiffuseLight {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let intensity = match j.get("intensity") {
+            Some(v) => safe_value_to_f64(v, 1.0),
+            None => 1.0
+        };
+
+        Ok(DiffuseLight {
+            emission: ObjectTexture::from_json(&j["emission"])?,
+            intensity,
+            two_sided: j["two_sided"].as_bool().unwrap_or(false)
+        })
+    }
+
+    fn emitted(&self, _ray: &Ray, hit: &HitInfo) -> Color {
+        if !hit.front_face && !self.two_sided {
+            return Color::BLACK;
+        }
+        self.emission.value(hit) * self.intensity
+    }
+
+    fn sample(&self, _ray: &Ray, _hit: &HitInfo, _sampler: &mut dyn Sampler, _srec: &mut ScatterRecord) -> bool {
+        false
+    }
+
+    // emission color without the intensity multiplier
+    fn albedo(&self, hit: &HitInfo) -> Color {
+        self.emission.value(hit)
+    }
+}
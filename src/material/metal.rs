@@ -0,0 +1,88 @@
+use crate::material::{Material, ScatterRecord};
+use crate::material::texture::{ObjectTexture, Texture};
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+use crate::util::*;
+use serde_json::{Value};
+use crate::sampler::Sampler;
+use glam::DVec3;
+
+// specular reflector, roughness spreads reflections into a phong lobe
+// around the mirror direction
+pub struct Metal {
+    pub albedo: ObjectTexture,
+    pub roughness: f64
+}
+
+impl Metal {
+    // phong exponent equivalent to the roughness
+    fn exponent(&self) -> f64 {
+        2.0 / (self.roughness * self.roughness) - 2.0
+    }
+}
+
+impl Material for Metal {
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let roughness = match j.get("roughness").or_else(|| j.get("fuzz")) {
+            Some(v) => safe_value_to_f64(v, 0.0),
+            None => 0.0
+        };
+
+        Ok(Metal {
+            albedo: ObjectTexture::from_json(&j["albedo"])?,
+            roughness: roughness.clamp(0.0, 1.0)
+        })
+    }
+
+    fn emitted(&self, _ray: &Ray, _hit: &HitInfo) -> Color {
+        Color::BLACK
+    }
+
+    fn sample(&self, ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler, srec: &mut ScatterRecord) -> bool {
+        let reflected = reflect(ray.d.normalize(), hit.sn);
+
+        if self.roughness == 0.0 {
+            srec.wo = reflected;
+            srec.attenuation = self.albedo.value(hit);
+            srec.is_specular = true;
+            return reflected.dot(hit.sn) > 0.0;
+        }
+
+        let rv = sampler.next_2d();
+        srec.wo = sample_phong_lobe(reflected, self.exponent(), rv.x, rv.y);
+        srec.is_specular = false;
+
+        // rays perturbed below the surface are absorbed
+        let pdf = self.pdf(ray, hit, srec.wo);
+        if srec.wo.dot(hit.sn) <= 0.0 || pdf == 0.0 {
+            return false;
+        }
+
+        srec.attenuation = self.eval(ray, hit, srec.wo) / pdf;
+        return true;
+    }
+
+    // normalized phong lobe around the mirror direction
+    fn eval(&self, ray: &Ray, hit: &HitInfo, wo: DVec3) -> Color {
+        let cos_theta = hit.sn.dot(wo);
+        if self.roughness == 0.0 || cos_theta <= 0.0 {
+            return Color::BLACK;
+        }
+        let reflected = reflect(ray.d.normalize(), hit.sn);
+        let cos_alpha = reflected.dot(wo).max(0.0);
+        let e = self.exponent();
+        self.albedo.value(hit) * ((e + 2.0) / (2.0 * M_PI) * cos_alpha.powf(e) * cos_theta)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &HitInfo, wo: DVec3) -> f64 {
+        if self.roughness == 0.0 {
+            return 0.0;
+        }
+        let reflected = reflect(ray.d.normalize(), hit.sn);
+        phong_lobe_pdf(reflected, self.exponent(), wo)
+    }
+
+    fn albedo(&self, hit: &HitInfo) -> Color {
+        self.albedo.value(hit)
+    }
+}
@@ -1,74 +1,185 @@
 pub mod texture;
+mod metal;
+mod dielectric;
+mod diffuse_light;
 
-use texture::{ConstantTexture, Texture};
-use image::Rgb;
+use texture::{ObjectTexture, Texture};
+use metal::Metal;
+use dielectric::Dielectric;
+use diffuse_light::DiffuseLight;
+use crate::util::color::Color;
 use crate::util::ray::{Ray, HitInfo};
 use crate::util::*;
-use rand::prelude::*;
+use crate::sampler::Sampler;
+use glam::DVec3;
 use serde_json::{Value};
 
 pub enum ObjectMaterial {
     LAMBERTIAN(Lambertian),
+    METAL(Metal),
+    DIELECTRIC(Dielectric),
+    DIFFUSELIGHT(DiffuseLight),
     EMPTY
 }
 
+impl ObjectMaterial {
+    // index of the material type, used to tell materials apart in debug output
+    pub fn kind_id(&self) -> u32 {
+        match self {
+            ObjectMaterial::LAMBERTIAN(_) => 1,
+            ObjectMaterial::METAL(_) => 2,
+            ObjectMaterial::DIELECTRIC(_) => 3,
+            ObjectMaterial::DIFFUSELIGHT(_) => 4,
+            ObjectMaterial::EMPTY => 0
+        }
+    }
+}
+
+// result of sampling a material
+pub struct ScatterRecord {
+    pub attenuation: Color,     // eval over pdf, or the tint of a specular event
+    pub wo: DVec3,              // sampled unit direction leaving the surface
+    pub is_specular: bool       // wo comes from a delta distribution, eval and pdf do not apply
+}
+
+impl ScatterRecord {
+    pub fn new() -> ScatterRecord {
+        ScatterRecord { attenuation: Color::BLACK, wo: DVec3::ZERO, is_specular: false }
+    }
+}
+
 pub trait Material {
     // amount of emitted light at surface hitpoint
-    fn emitted(&self, ray: &Ray, hit: &HitInfo) -> Rgb<u8>;
+    fn emitted(&self, ray: &Ray, hit: &HitInfo) -> Color;
+
+    // sample a scattered direction for a ray arriving at the hitpoint
+    // return false if the ray is absorbed
+    fn sample(&self, ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler, srec: &mut ScatterRecord) -> bool;
+
+    // bsdf times cosine for light arriving from unit direction wo and leaving along -ray.d
+    fn eval(&self, _ray: &Ray, _hit: &HitInfo, _wo: DVec3) -> Color { Color::BLACK }
+
+    // solid angle density of sample() producing unit direction wo
+    fn pdf(&self, _ray: &Ray, _hit: &HitInfo, _wo: DVec3) -> f64 { 0.0 }
+
+    // surface color independent of lighting, written to the albedo aov
+    fn albedo(&self, _hit: &HitInfo) -> Color { Color::BLACK }
 
-    // scattered direction at surface hitpoint
-    // return attenuation if surface scatters light
-    fn scatter(&self, ray: &Ray, hit: &HitInfo, scattered: &mut Ray) -> Option<Rgb<u8>>;
+    fn from_json(j: &Value) -> Result<Self, String> where Self: Sized;
 
-    fn from_json(j: &Value) -> Self;
+    // whether the material emits light, used to build the light list
+    fn is_emissive(&self) -> bool { false }
 }
 
 pub struct Lambertian {
-    pub albedo: ConstantTexture
+    pub albedo: ObjectTexture
 }
 
 impl Material for Lambertian {
-    fn from_json(j: &Value) -> Self {
-        Lambertian { albedo: ConstantTexture::from_json(j) }
+    fn from_json(j: &Value) -> Result<Self, String> {
+        Ok(Lambertian { albedo: ObjectTexture::from_json(&j["albedo"])? })
     }
 
-    fn emitted(&self, ray: &Ray, hit: &HitInfo) -> Rgb<u8> {
-        image::Rgb([0, 0, 0])
+    fn emitted(&self, _ray: &Ray, _hit: &HitInfo) -> Color {
+        Color::BLACK
     }
 
-    fn scatter(&self, ray: &Ray, hit: &HitInfo, scattered: &mut Ray) -> Option<Rgb<u8>> {
-        let attenuation = self.albedo.value(hit);
+    // cosine weighted direction around the shading normal
+    fn sample(&self, _ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler, srec: &mut ScatterRecord) -> bool {
+        let rv = sampler.next_2d();
+        let d = hit.sn + random_on_unit_sphere(rv.x, rv.y);
+        if d.length_squared() < 1e-12 {
+            return false;
+        }
+
+        srec.wo = d.normalize();
+        srec.attenuation = self.albedo.value(hit);
+        srec.is_specular = false;
+        return true;
+    }
 
-        // get scattered ray
-        scattered.o = hit.p;
-        scattered.d = hit.sn + random_on_unit_sphere(random::<f64>(), random::<f64>());
+    fn eval(&self, _ray: &Ray, hit: &HitInfo, wo: DVec3) -> Color {
+        let cos_theta = hit.sn.dot(wo).max(0.0);
+        self.albedo.value(hit) * (cos_theta / M_PI)
+    }
 
-        return Some(attenuation);
+    fn pdf(&self, _ray: &Ray, hit: &HitInfo, wo: DVec3) -> f64 {
+        hit.sn.dot(wo).max(0.0) / M_PI
+    }
+
+    fn albedo(&self, hit: &HitInfo) -> Color {
+        self.albedo.value(hit)
     }
 }
 
 impl Material for ObjectMaterial {
-    fn from_json(j: &Value) -> Self {
-        let material_type = j.get("type").unwrap().as_str().unwrap();
+    fn from_json(j: &Value) -> Result<Self, String> {
+        let material_type = match j["type"].as_str() {
+            Some(t) => t,
+            None => return Err(String::from("material without type"))
+        };
 
-        if material_type == "lambertian" {
-            return ObjectMaterial::LAMBERTIAN(Lambertian::from_json(j.get("albedo").unwrap()));
-        } else {
-            panic!("could not parse material")
-        }
+        let material = match material_type {
+            "lambertian" => ObjectMaterial::LAMBERTIAN(Lambertian::from_json(j)?),
+            "metal" => ObjectMaterial::METAL(Metal::from_json(j)?),
+            "dielectric" => ObjectMaterial::DIELECTRIC(Dielectric::from_json(j)?),
+            "diffuse_light" => ObjectMaterial::DIFFUSELIGHT(DiffuseLight::from_json(j)?),
+            _ => return Err(format!("could not parse material \"{}\"", material_type))
+        };
+        Ok(material)
+    }
+
+    fn is_emissive(&self) -> bool {
+        matches!(self, ObjectMaterial::DIFFUSELIGHT(_))
     }
 
-    fn emitted(&self, ray: &Ray, hit: &HitInfo) -> Rgb<u8> {
+    fn emitted(&self, ray: &Ray, hit: &HitInfo) -> Color {
         match self {
             ObjectMaterial::LAMBERTIAN(v) => v.emitted(ray, hit),
-            ObjectMaterial::EMPTY => Rgb([0, 0, 0])
+            ObjectMaterial::METAL(v) => v.emitted(ray, hit),
+            ObjectMaterial::DIELECTRIC(v) => v.emitted(ray, hit),
+            ObjectMaterial::DIFFUSELIGHT(v) => v.emitted(ray, hit),
+            ObjectMaterial::EMPTY => Color::BLACK
+        }
+    }
+
+    fn sample(&self, ray: &Ray, hit: &HitInfo, sampler: &mut dyn Sampler, srec: &mut ScatterRecord) -> bool {
+        match self {
+            ObjectMaterial::LAMBERTIAN(v) => v.sample(ray, hit, sampler, srec),
+            ObjectMaterial::METAL(v) => v.sample(ray, hit, sampler, srec),
+            ObjectMaterial::DIELECTRIC(v) => v.sample(ray, hit, sampler, srec),
+            ObjectMaterial::DIFFUSELIGHT(v) => v.sample(ray, hit, sampler, srec),
+            ObjectMaterial::EMPTY => false
+        }
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitInfo, wo: DVec3) -> Color {
+        match self {
+            ObjectMaterial::LAMBERTIAN(v) => v.eval(ray, hit, wo),
+            ObjectMaterial::METAL(v) => v.eval(ray, hit, wo),
+            ObjectMaterial::DIELECTRIC(v) => v.eval(ray, hit, wo),
+            ObjectMaterial::DIFFUSELIGHT(v) => v.eval(ray, hit, wo),
+            ObjectMaterial::EMPTY => Color::BLACK
+        }
+    }
+
+    fn albedo(&self, hit: &HitInfo) -> Color {
+        match self {
+            ObjectMaterial::LAMBERTIAN(v) => v.albedo(hit),
+            ObjectMaterial::METAL(v) => v.albedo(hit),
+            ObjectMaterial::DIELECTRIC(v) => v.albedo(hit),
+            ObjectMaterial::DIFFUSELIGHT(v) => v.albedo(hit),
+            ObjectMaterial::EMPTY => Color::BLACK
         }
     }
 
-    fn scatter(&self, ray: &Ray, hit: &HitInfo, scattered: &mut Ray) -> Option<Rgb<u8>> {
+    fn pdf(&self, ray: &Ray, hit: &HitInfo, wo: DVec3) -> f64 {
         match self {
-            ObjectMaterial::LAMBERTIAN(v) => v.scatter(ray, hit, scattered),
-            ObjectMaterial::EMPTY => None
+            ObjectMaterial::LAMBERTIAN(v) => v.pdf(ray, hit, wo),
+            ObjectMaterial::METAL(v) => v.pdf(ray, hit, wo),
+            ObjectMaterial::DIELECTRIC(v) => v.pdf(ray, hit, wo),
+            ObjectMaterial::DIFFUSELIGHT(v) => v.pdf(ray, hit, wo),
+            ObjectMaterial::EMPTY => 0.0
         }
     }
 }
\ No newline at end of file
@@ -1,24 +1,170 @@
-use crate::util::ray::HitInfo;
 use crate::util::*;
-use image::Rgb;
+use crate::util::ray::HitInfo;
+use crate::util::color::{Color, srgb_to_linear};
+use image::Rgb32FImage;
+use glam::DVec2;
 use serde_json::{Value};
 
+pub enum ObjectTexture {
+    CONSTANT(ConstantTexture),
+    IMAGE(ImageTexture),
+    VERTEXCOLOR
+}
+
 pub trait Texture {
-    fn value(&self, hit: &HitInfo) -> Rgb<u8>; 
+    fn value(&self, hit: &HitInfo) -> Color;
+}
+
+impl ObjectTexture {
+    // a color is a constant texture, other objects select the type with "type"
+    pub fn from_json(v: &Value) -> Result<ObjectTexture, String> {
+        if !v.is_object() || v.get("srgb8").is_some() {
+            return Ok(ObjectTexture::CONSTANT(ConstantTexture::from_json(v)?));
+        }
+
+        let texture_type = v["type"].as_str().unwrap_or("constant");
+        let texture = match texture_type {
+            "constant" => ObjectTexture::CONSTANT(ConstantTexture::from_json(&v["color"])?),
+            "image" => ObjectTexture::IMAGE(ImageTexture::from_json(v)?),
+            "vertex_color" => ObjectTexture::VERTEXCOLOR,
+            _ => return Err(format!("could not parse texture \"{}\"", texture_type))
+        };
+        Ok(texture)
+    }
+}
+
+impl Texture for ObjectTexture {
+    fn value(&self, hit: &HitInfo) -> Color {
+        match self {
+            ObjectTexture::CONSTANT(t) => t.value(hit),
+            ObjectTexture::IMAGE(t) => t.value(hit),
+            // colors of the mesh vertices, white on other surfaces
+            ObjectTexture::VERTEXCOLOR => hit.vertex_color
+        }
+    }
 }
 
 pub struct ConstantTexture {
-    pub color: Rgb<u8>
+    pub color: Color
 }
 
 impl ConstantTexture {
-    pub fn from_json(v: &Value) -> ConstantTexture {
-        ConstantTexture { color: safe_value_to_color(v, Rgb([0, 0, 0])) }
+    pub fn from_json(v: &Value) -> Result<ConstantTexture, String> {
+        Ok(ConstantTexture { color: Color::from_json(v, Color::BLACK)? })
     }
 }
 
 impl Texture for ConstantTexture {
-    fn value(&self, hit: &HitInfo) -> Rgb<u8> {
+    fn value(&self, _hit: &HitInfo) -> Color {
         self.color
     }
-}
\ No newline at end of file
+}
+
+// handling of texture coordinates outside [0, 1]
+#[derive(Clone, Copy)]
+enum WrapMode {
+    REPEAT,
+    CLAMP,
+    MIRROR
+}
+
+impl WrapMode {
+    // map an integer texel coordinate into [0, n)
+    fn apply(&self, i: i64, n: i64) -> i64 {
+        match self {
+            WrapMode::REPEAT => i.rem_euclid(n),
+            WrapMode::CLAMP => i.clamp(0, n - 1),
+            WrapMode::MIRROR => {
+                let k = i.rem_euclid(2 * n);
+                if k < n { k } else { 2 * n - 1 - k }
+            }
+        }
+    }
+}
+
+// color looked up from an image file by the hit texture coordinates,
+// v runs from the bottom to the top of the image
+pub struct ImageTexture {
+    m_image: Rgb32FImage,   // linear values
+    m_wrap: WrapMode,
+    m_bilinear: bool,       // interpolate between texels instead of taking the nearest
+    m_scale: DVec2          // texture coordinate multiplier, for tiling
+}
+
+impl ImageTexture {
+    pub fn from_json(j: &Value) -> Result<ImageTexture, String> {
+        let filename = match j["filename"].as_str() {
+            Some(v) => v,
+            None => return Err(String::from("can't parse image texture without filename"))
+        };
+
+        let image = match image::open(filename) {
+            Ok(i) => i,
+            Err(e) => return Err(format!("error loading texture \"{}\": {}", filename, e))
+        };
+
+        // 8 and 16 bit images are srgb encoded like srgb8 json colors, float images are linear
+        let is_float = matches!(image.color(), image::ColorType::Rgb32F | image::ColorType::Rgba32F);
+        let srgb = j["srgb"].as_bool().unwrap_or(!is_float);
+        let mut image = image.to_rgb32f();
+        if srgb {
+            for p in image.pixels_mut() {
+                for c in p.0.iter_mut() {
+                    *c = srgb_to_linear(*c as f64) as f32;
+                }
+            }
+        }
+
+        let wrap = match j["wrap"].as_str().unwrap_or("repeat") {
+            "repeat" => WrapMode::REPEAT,
+            "clamp" => WrapMode::CLAMP,
+            "mirror" => WrapMode::MIRROR,
+            other => return Err(format!("unknown wrap mode \"{}\"", other))
+        };
+
+        let bilinear = match j["filter"].as_str().unwrap_or("bilinear") {
+            "bilinear" => true,
+            "nearest" => false,
+            other => return Err(format!("unknown texture filter \"{}\"", other))
+        };
+
+        let scale = match j.get("scale") {
+            Some(Value::Array(s)) if s.len() >= 2 => DVec2 { x: safe_value_to_f64(&s[0], 1.0), y: safe_value_to_f64(&s[1], 1.0) },
+            Some(v) => DVec2::splat(safe_value_to_f64(v, 1.0)),
+            None => DVec2::ONE
+        };
+
+        Ok(ImageTexture { m_image: image, m_wrap: wrap, m_bilinear: bilinear, m_scale: scale })
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = self.m_wrap.apply(x, self.m_image.width() as i64);
+        let y = self.m_wrap.apply(y, self.m_image.height() as i64);
+        let p = self.m_image.get_pixel(x as u32, y as u32);
+        Color::new(p[0] as f64, p[1] as f64, p[2] as f64)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, hit: &HitInfo) -> Color {
+        let uv = hit.uv * self.m_scale;
+
+        // continuous texel coordinates, texel centers are at half integers
+        let x = uv.x * self.m_image.width() as f64;
+        let y = (1.0 - uv.y) * self.m_image.height() as f64;
+
+        if !self.m_bilinear {
+            return self.texel(x.floor() as i64, y.floor() as i64);
+        }
+
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        self.texel(x0, y0) * ((1.0 - fx) * (1.0 - fy))
+            + self.texel(x0 + 1, y0) * (fx * (1.0 - fy))
+            + self.texel(x0, y0 + 1) * ((1.0 - fx) * fy)
+            + self.texel(x0 + 1, y0 + 1) * (fx * fy)
+    }
+}
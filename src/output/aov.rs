@@ -0,0 +1,75 @@
+use crate::material::Material;
+use crate::util::color::Color;
+use crate::util::ray::{Ray, HitInfo};
+
+// auxiliary per-pixel buffer filled from the first hit of each camera sample
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aov {
+    ALBEDO,     // material color without lighting
+    NORMAL,     // world space shading normal
+    DEPTH,      // distance from the camera
+    POSITION,   // world space hit position
+    OBJECTID    // index of the surface entry plus one, 0 for the background
+}
+
+impl Aov {
+    pub fn from_name(name: &str) -> Option<Aov> {
+        match name {
+            "albedo" => Some(Aov::ALBEDO),
+            "normal" => Some(Aov::NORMAL),
+            "depth" => Some(Aov::DEPTH),
+            "position" => Some(Aov::POSITION),
+            "object_id" | "id" => Some(Aov::OBJECTID),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::ALBEDO => "albedo",
+            Aov::NORMAL => "normal",
+            Aov::DEPTH => "depth",
+            Aov::POSITION => "position",
+            Aov::OBJECTID => "object_id"
+        }
+    }
+
+    // exr channel names, values beyond the listed channels are not written
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::ALBEDO => &["R", "G", "B"],
+            Aov::NORMAL | Aov::POSITION => &["X", "Y", "Z"],
+            Aov::DEPTH => &["Z"],
+            Aov::OBJECTID => &["id"]
+        }
+    }
+
+    // ids can not be blended, the pixel keeps the value of its first sample
+    pub fn is_averaged(&self) -> bool {
+        *self != Aov::OBJECTID
+    }
+
+    // value for a camera ray, hit is None if the ray left the scene
+    // single channel buffers repeat the value in all components
+    pub fn value(&self, ray: &Ray, hit: Option<&HitInfo>) -> Color {
+        let hit = match hit {
+            Some(h) => h,
+            None => return Color::BLACK
+        };
+
+        match self {
+            Aov::ALBEDO => hit.mat.as_ref().albedo(hit),
+            Aov::NORMAL => Color::new(hit.sn.x, hit.sn.y, hit.sn.z),
+            Aov::DEPTH => {
+                // camera rays are not normalized
+                let d = hit.t * ray.d.length();
+                Color::new(d, d, d)
+            },
+            Aov::POSITION => Color::new(hit.p.x, hit.p.y, hit.p.z),
+            Aov::OBJECTID => {
+                let id = (hit.object_id + 1) as f64;
+                Color::new(id, id, id)
+            }
+        }
+    }
+}
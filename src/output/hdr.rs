@@ -0,0 +1,81 @@
+use crate::output::aov::Aov;
+use image::Rgb32FImage;
+use image::codecs::hdr::HdrEncoder;
+use exr::prelude::{f16, AnyChannel, AnyChannels, FlatSamples, Image, Layer, LayerAttributes, Encoding, SmallVec, WritableImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+// radiance rgbe (.hdr) file
+pub fn write_rgbe(path: &str, img: &Rgb32FImage) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let pixels: Vec<image::Rgb<f32>> = img.pixels().copied().collect();
+    HdrEncoder::new(BufWriter::new(file))
+        .encode(&pixels, img.width() as usize, img.height() as usize)
+        .map_err(|e| e.to_string())
+}
+
+// portable float map (.pfm), rows are stored bottom to top
+pub fn write_pfm(path: &str, img: &Rgb32FImage) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = BufWriter::new(file);
+
+    // negative scale marks little endian data
+    write!(writer, "PF\n{} {}\n-1.0\n", img.width(), img.height()).map_err(|e| e.to_string())?;
+    for y in (0..img.height()).rev() {
+        for x in 0..img.width() {
+            for c in img.get_pixel(x, y).0 {
+                writer.write_all(&c.to_le_bytes()).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+// openexr file with half or full float rgb channels
+pub fn write_exr(path: &str, img: &Rgb32FImage, half: bool) -> Result<(), String> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let result = if half {
+        exr::prelude::write_rgb_file(path, width, height, |x, y| {
+            let p = img.get_pixel(x as u32, y as u32);
+            (f16::from_f32(p[0]), f16::from_f32(p[1]), f16::from_f32(p[2]))
+        })
+    } else {
+        exr::prelude::write_rgb_file(path, width, height, |x, y| {
+            let p = img.get_pixel(x as u32, y as u32);
+            (p[0], p[1], p[2])
+        })
+    };
+    result.map_err(|e| e.to_string())
+}
+
+// openexr file with the image in the rgb channels and every aov as a group
+// of full float channels named like "albedo.R" or "depth.Z"
+pub fn write_exr_layers(path: &str, img: &Rgb32FImage, aovs: &[(Aov, Rgb32FImage)], half: bool) -> Result<(), String> {
+    let channel = |buffer: &Rgb32FImage, c: usize| -> Vec<f32> {
+        buffer.pixels().map(|p| p[c]).collect()
+    };
+
+    let mut channels: Vec<AnyChannel<FlatSamples>> = Vec::new();
+    for (c, name) in ["R", "G", "B"].iter().enumerate() {
+        let samples = if half {
+            FlatSamples::F16(channel(img, c).into_iter().map(f16::from_f32).collect())
+        } else {
+            FlatSamples::F32(channel(img, c))
+        };
+        channels.push(AnyChannel::new(*name, samples));
+    }
+    for (aov, buffer) in aovs.iter() {
+        for (c, name) in aov.channels().iter().enumerate() {
+            let name = format!("{}.{}", aov.name(), name);
+            channels.push(AnyChannel::new(name.as_str(), FlatSamples::F32(channel(buffer, c))));
+        }
+    }
+
+    let layer = Layer::new(
+        (img.width() as usize, img.height() as usize),
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels))
+    );
+    Image::from_layer(layer).write().to_file(path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,163 @@
+pub mod tonemap;
+pub mod hdr;
+pub mod aov;
+
+use serde_json::{Value};
+use image::Rgb32FImage;
+use tonemap::ToneMapper;
+use aov::Aov;
+
+// file format of the rendered image
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    LDR,    // tone mapped 8 bit image in any format the image crate can write
+    RGBE,   // radiance .hdr
+    PFM,    // portable float map
+    EXR     // openexr
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name.to_lowercase().as_str() {
+            "hdr" | "rgbe" => Some(ImageFormat::RGBE),
+            "pfm" => Some(ImageFormat::PFM),
+            "exr" => Some(ImageFormat::EXR),
+            "png" | "jpg" | "jpeg" | "bmp" | "tga" | "tif" | "tiff" | "ppm" => Some(ImageFormat::LDR),
+            _ => None
+        }
+    }
+
+    // guess format from file extension, defaulting to ldr
+    pub fn from_path(path: &str) -> ImageFormat {
+        let extension = match path.rsplit_once('.') {
+            Some((_, e)) => e,
+            None => ""
+        };
+        ImageFormat::from_name(extension).unwrap_or(ImageFormat::LDR)
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::LDR => "png",
+            ImageFormat::RGBE => "hdr",
+            ImageFormat::PFM => "pfm",
+            ImageFormat::EXR => "exr"
+        }
+    }
+}
+
+// image settings of the scene, keys of the "film" block override those of "output"
+pub fn settings_block(scene: &Value) -> Value {
+    let mut merged = serde_json::Map::new();
+    for key in ["output", "film"] {
+        if let Some(block) = scene[key].as_object() {
+            for (k, v) in block {
+                merged.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    Value::Object(merged)
+}
+
+// framebuffer of a finished render
+pub struct RenderedImage {
+    pub radiance: Rgb32FImage,
+    pub aovs: Vec<(Aov, Rgb32FImage)>     // in the order of OutputSettings::aovs
+}
+
+// how the framebuffer is written to disk
+pub struct OutputSettings {
+    pub format: Option<ImageFormat>,    // forced format, otherwise chosen by extension
+    pub exr_half: bool,                 // write half instead of full float exr channels
+    pub aovs: Vec<Aov>,                 // extra buffers rendered alongside the image, each
+                                        // camera sample traces one more primary ray for them
+    pub aov_layers: Option<bool>        // aovs go into the exr image instead of their own files,
+                                        // by default when the image is written as exr
+}
+
+impl OutputSettings {
+    // parse from the merged "output" and "film" blocks of the scene
+    pub fn from_json(scene: &Value) -> Result<OutputSettings, String> {
+        let j = &settings_block(scene);
+
+        let format = match j.get("format").and_then(|v| v.as_str()) {
+            Some(f) => match ImageFormat::from_name(f) {
+                Some(format) => Some(format),
+                None => return Err(format!("unknown output format \"{}\"", f))
+            },
+            None => None
+        };
+
+        let exr_half = match j.get("exr_precision").and_then(|v| v.as_str()) {
+            Some("half") | None => true,
+            Some("float") => false,
+            Some(other) => return Err(format!("unknown exr precision \"{}\"", other))
+        };
+
+        let aovs = match j.get("aovs").and_then(|v| v.as_array()) {
+            Some(a) => a.iter().map(|v| {
+                let name = v.as_str().unwrap_or("");
+                match Aov::from_name(name) {
+                    Some(aov) => Ok(aov),
+                    None => Err(format!("unknown aov \"{}\"", name))
+                }
+            }).collect::<Result<Vec<Aov>, String>>()?,
+            None => Vec::new()
+        };
+
+        let aov_layers = match j.get("aov_output").and_then(|v| v.as_str()) {
+            Some("layers") => Some(true),
+            Some("files") => Some(false),
+            None => None,
+            Some(other) => return Err(format!("unknown aov output \"{}\"", other))
+        };
+
+        Ok(OutputSettings { format, exr_half, aovs, aov_layers })
+    }
+}
+
+// path of a separately written aov, "image.png" becomes "image_albedo.exr"
+fn aov_path(path: &str, aov: Aov, format: ImageFormat) -> String {
+    let stem = match path.rsplit_once('.') {
+        Some((stem, e)) if !e.contains('/') => stem,
+        _ => path
+    };
+    format!("{}_{}.{}", stem, aov.name(), format.extension())
+}
+
+// write the linear framebuffer, tone mapping it first for ldr formats
+// aovs are never tone mapped and hold signed values that ldr and rgbe can not
+// store, so they are written as exr unless the image itself is pfm
+pub fn save_image(path: &str, image: &RenderedImage, tonemapper: &ToneMapper, settings: &OutputSettings) -> Result<(), String> {
+    let format = settings.format.unwrap_or_else(|| ImageFormat::from_path(path));
+    let radiance = &image.radiance;
+
+    let layers = !image.aovs.is_empty() && settings.aov_layers.unwrap_or(format == ImageFormat::EXR);
+    if layers && format != ImageFormat::EXR {
+        return Err(String::from("aov layers can only be written to exr images"));
+    }
+
+    match format {
+        ImageFormat::LDR => tonemapper.apply(radiance).save(path).map_err(|e| e.to_string())?,
+        ImageFormat::RGBE => hdr::write_rgbe(path, radiance)?,
+        ImageFormat::PFM => hdr::write_pfm(path, radiance)?,
+        ImageFormat::EXR if layers => hdr::write_exr_layers(path, radiance, &image.aovs, settings.exr_half)?,
+        ImageFormat::EXR => hdr::write_exr(path, radiance, settings.exr_half)?
+    }
+
+    if layers {
+        return Ok(());
+    }
+
+    let aov_format = if format == ImageFormat::PFM { ImageFormat::PFM } else { ImageFormat::EXR };
+    for (aov, buffer) in image.aovs.iter() {
+        let aov_path = aov_path(path, *aov, aov_format);
+        let result = match aov_format {
+            ImageFormat::PFM => hdr::write_pfm(&aov_path, buffer),
+            _ => hdr::write_exr(&aov_path, buffer, false)
+        };
+        result.map_err(|e| format!("{}: {}", aov_path, e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use serde_json::{Value};
+use image::{Rgb32FImage, RgbImage};
+use crate::util::*;
+use crate::util::color::linear_to_srgb;
+
+// curve mapping unbounded radiance into [0, 1]
+#[derive(Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    CLAMP,
+    REINHARD,
+    EXTENDEDREINHARD,
+    ACES,
+    HABLE
+}
+
+// converts the linear framebuffer into a displayable 8 bit image
+pub struct ToneMapper {
+    pub operator: ToneMapOperator,
+    pub exposure: f64,      // in stops, radiance is scaled by 2^exposure
+    pub white_point: f64,   // smallest value mapped to white by extended reinhard
+    pub srgb: bool          // apply the srgb transfer function
+}
+
+impl ToneMapper {
+    // parse from the merged "output" and "film" blocks of the scene
+    pub fn from_json(scene: &Value) -> Result<ToneMapper, String> {
+        let j = &super::settings_block(scene);
+
+        let operator = match j.get("tonemap").and_then(|v| v.as_str()) {
+            Some("clamp") | None => ToneMapOperator::CLAMP,
+            Some("reinhard") => ToneMapOperator::REINHARD,
+            Some("extended_reinhard") => ToneMapOperator::EXTENDEDREINHARD,
+            Some("aces") => ToneMapOperator::ACES,
+            Some("hable") | Some("filmic") => ToneMapOperator::HABLE,
+            Some(other) => return Err(format!("unknown tone mapping operator \"{}\"", other))
+        };
+
+        Ok(ToneMapper {
+            operator,
+            exposure: safe_value_to_f64(&j["exposure"], 0.0),
+            white_point: safe_value_to_f64(&j["white_point"], 4.0),
+            srgb: j["srgb"].as_bool().unwrap_or(true)
+        })
+    }
+
+    // map a single linear channel value to [0, 1]
+    pub fn map(&self, x: f64) -> f64 {
+        let x = (x * 2.0_f64.powf(self.exposure)).max(0.0);
+        let mapped = match self.operator {
+            ToneMapOperator::CLAMP => x,
+            ToneMapOperator::REINHARD => x / (1.0 + x),
+            ToneMapOperator::EXTENDEDREINHARD => {
+                x * (1.0 + x / (self.white_point * self.white_point)) / (1.0 + x)
+            },
+            ToneMapOperator::ACES => aces_filmic(x),
+            ToneMapOperator::HABLE => {
+                // exposure bias and linear white point from the original curve
+                hable_partial(2.0 * x) / hable_partial(11.2)
+            }
+        };
+        let mapped = mapped.clamp(0.0, 1.0);
+
+        if self.srgb {
+            linear_to_srgb(mapped)
+        } else {
+            mapped
+        }
+    }
+
+    // tone map every pixel and quantize to 8 bits
+    pub fn apply(&self, img: &Rgb32FImage) -> RgbImage {
+        let mut out = RgbImage::new(img.width(), img.height());
+        for (src, dst) in img.pixels().zip(out.pixels_mut()) {
+            for c in 0..3 {
+                dst[c] = (self.map(src[c] as f64) * 255.0).round() as u8;
+            }
+        }
+        out
+    }
+}
+
+// fitted aces reference rendering transform (Narkowicz 2015)
+fn aces_filmic(x: f64) -> f64 {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+// filmic curve from Uncharted 2 (Hable 2010)
+fn hable_partial(x: f64) -> f64 {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
@@ -0,0 +1,80 @@
+use crate::sampler::{Sampler, ONE_MINUS_EPSILON, hash_to_f64, permutation_element};
+use crate::util::*;
+use std::sync::OnceLock;
+
+// number of dimensions with their own prime base, later dimensions
+// fall back to hashed random numbers
+const HALTON_DIMENSIONS: usize = 256;
+
+// first HALTON_DIMENSIONS primes
+fn primes() -> &'static [u32] {
+    static PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let mut primes: Vec<u32> = Vec::with_capacity(HALTON_DIMENSIONS);
+        let mut n = 2;
+        while primes.len() < HALTON_DIMENSIONS {
+            if primes.iter().take_while(|p| *p * *p <= n).all(|p| n % p != 0) {
+                primes.push(n);
+            }
+            n += 1;
+        }
+        primes
+    })
+}
+
+// radical inverse of a in the given base with every digit permuted
+// depending on the digits below it (owen scrambling)
+fn owen_scrambled_radical_inverse(base: u32, a: u64, hash: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut reversed_digits: u64 = 0;
+    let mut a = a;
+
+    // keep going past the last non-zero digit until the precision is used up
+    while 1.0 - inv_base_m < 1.0 {
+        let next = a / base as u64;
+        let digit = (a - next * base as u64) as u32;
+        let digit_hash = hash_u64(hash ^ reversed_digits);
+        let digit = permutation_element(digit, base, digit_hash as u32);
+        reversed_digits = reversed_digits * base as u64 + digit as u64;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+
+    (inv_base_m * reversed_digits as f64).min(ONE_MINUS_EPSILON)
+}
+
+// halton sequence using dimension d in base of the d-th prime, each pixel
+// uses its own scrambling of the sequence
+#[derive(Clone)]
+pub struct HaltonSampler {
+    m_seed: u64,
+    m_pixel_hash: u64,
+    m_index: u32,
+    m_dimension: usize
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler { m_seed: seed, m_pixel_hash: 0, m_index: 0, m_dimension: 0 }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.m_pixel_hash = pixel_seed(self.m_seed, x, y);
+        self.m_index = index;
+        self.m_dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let hash = hash_u64(self.m_pixel_hash ^ hash_u64(self.m_dimension as u64));
+        let d = self.m_dimension;
+        self.m_dimension += 1;
+
+        if d >= HALTON_DIMENSIONS {
+            return hash_to_f64(hash_u64(hash ^ self.m_index as u64));
+        }
+        owen_scrambled_radical_inverse(primes()[d], self.m_index as u64, hash)
+    }
+}
@@ -0,0 +1,162 @@
+mod stratified;
+mod halton;
+mod sobol;
+
+use stratified::StratifiedSampler;
+use halton::HaltonSampler;
+use sobol::SobolSampler;
+use crate::util::*;
+use glam::DVec2;
+use serde_json::{Value};
+
+// largest f64 below one
+pub const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+// source of the random numbers consumed by a single camera sample
+// every call to next_1d or next_2d moves on to the next dimension(s)
+#[derive(Clone)]
+pub enum ObjectSampler {
+    INDEPENDENT(IndependentSampler),
+    STRATIFIED(StratifiedSampler),
+    HALTON(HaltonSampler),
+    SOBOL(SobolSampler)
+}
+
+pub trait Sampler {
+    // restart at the first dimension of sample index of pixel (x, y)
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32);
+
+    fn next_1d(&mut self) -> f64;
+
+    fn next_2d(&mut self) -> DVec2 {
+        DVec2 { x: self.next_1d(), y: self.next_1d() }
+    }
+}
+
+impl ObjectSampler {
+    // samples is the number of samples per pixel, seed decides all scrambling
+    pub fn from_json(j: &Value, samples: i64, seed: u64) -> Result<ObjectSampler, String> {
+        let sampler_type = j["type"].as_str().unwrap_or("independent");
+        let samples = samples.max(1) as u32;
+
+        let sampler = match sampler_type {
+            "independent" => ObjectSampler::INDEPENDENT(IndependentSampler::new(seed)),
+            "stratified" => ObjectSampler::STRATIFIED(StratifiedSampler::new(samples, seed)),
+            "halton" => ObjectSampler::HALTON(HaltonSampler::new(seed)),
+            "sobol" => ObjectSampler::SOBOL(SobolSampler::new(seed)),
+            _ => return Err(format!("could not parse sampler \"{}\"", sampler_type))
+        };
+        Ok(sampler)
+    }
+}
+
+impl Sampler for ObjectSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        match self {
+            ObjectSampler::INDEPENDENT(v) => v.start_pixel_sample(x, y, index),
+            ObjectSampler::STRATIFIED(v) => v.start_pixel_sample(x, y, index),
+            ObjectSampler::HALTON(v) => v.start_pixel_sample(x, y, index),
+            ObjectSampler::SOBOL(v) => v.start_pixel_sample(x, y, index)
+        }
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        match self {
+            ObjectSampler::INDEPENDENT(v) => v.next_1d(),
+            ObjectSampler::STRATIFIED(v) => v.next_1d(),
+            ObjectSampler::HALTON(v) => v.next_1d(),
+            ObjectSampler::SOBOL(v) => v.next_1d()
+        }
+    }
+
+    fn next_2d(&mut self) -> DVec2 {
+        match self {
+            ObjectSampler::INDEPENDENT(v) => v.next_2d(),
+            ObjectSampler::STRATIFIED(v) => v.next_2d(),
+            ObjectSampler::HALTON(v) => v.next_2d(),
+            ObjectSampler::SOBOL(v) => v.next_2d()
+        }
+    }
+}
+
+// uncorrelated uniform numbers, every pixel sample has its own splitmix64 stream
+#[derive(Clone)]
+pub struct IndependentSampler {
+    m_seed: u64,
+    m_state: u64
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> IndependentSampler {
+        IndependentSampler { m_seed: seed, m_state: seed }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.m_state = hash_u64(pixel_seed(self.m_seed, x, y) ^ index as u64);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        // hash_u64 adds the splitmix64 increment itself
+        let v = hash_u64(self.m_state);
+        self.m_state = self.m_state.wrapping_add(0x9e3779b97f4a7c15);
+        hash_to_f64(v)
+    }
+}
+
+// uniform number in [0, 1) from the high bits of a hash
+pub fn hash_to_f64(h: u64) -> f64 {
+    (h >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+// element i of a pseudo random permutation of [0, n) picked by seed
+// (Kensler, correlated multi-jittered sampling)
+pub fn permutation_element(i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let p = seed;
+    let mut i = i;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        // cycle walk until the value falls inside [0, n)
+        if i < n {
+            break;
+        }
+    }
+    ((i as u64 + p as u64) % n as u64) as u32
+}
+
+// owen scrambling of the bits of v, most significant bit first
+// (Burley, practical hash-based owen scrambling)
+pub fn nested_uniform_scramble(v: u32, seed: u32) -> u32 {
+    let mut x = v.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
@@ -0,0 +1,70 @@
+use crate::sampler::{Sampler, ONE_MINUS_EPSILON, nested_uniform_scramble};
+use crate::util::*;
+use glam::DVec2;
+
+// second dimension of the sobol sequence, the first is the bit reversed index
+fn sobol_second(index: u32) -> u32 {
+    let mut v: u32 = 1 << 31;
+    let mut result = 0;
+    let mut i = index;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn to_unit(v: u32) -> f64 {
+    (v as f64 / 4294967296.0).min(ONE_MINUS_EPSILON)
+}
+
+// padded owen scrambled sobol, every 1d or 2d request uses the first one or
+// two sobol dimensions with a per dimension shuffle of the sample index, so
+// all pairs of dimensions stay well distributed for power of two sample counts
+#[derive(Clone)]
+pub struct SobolSampler {
+    m_seed: u64,
+    m_pixel_hash: u64,
+    m_index: u32,
+    m_dimension: u64
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> SobolSampler {
+        SobolSampler { m_seed: seed, m_pixel_hash: 0, m_index: 0, m_dimension: 0 }
+    }
+
+    // shuffled index and two scrambling seeds for the current dimension
+    fn next_dimension(&mut self) -> (u32, u32, u32) {
+        let h = hash_u64(self.m_pixel_hash ^ hash_u64(self.m_dimension));
+        let h2 = hash_u64(h);
+        self.m_dimension += 1;
+
+        let index = nested_uniform_scramble(self.m_index, h as u32);
+        (index, (h >> 32) as u32, h2 as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.m_pixel_hash = pixel_seed(self.m_seed, x, y);
+        self.m_index = index;
+        self.m_dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let (index, seed, _) = self.next_dimension();
+        to_unit(nested_uniform_scramble(index.reverse_bits(), seed))
+    }
+
+    fn next_2d(&mut self) -> DVec2 {
+        let (index, seed_x, seed_y) = self.next_dimension();
+        DVec2 {
+            x: to_unit(nested_uniform_scramble(index.reverse_bits(), seed_x)),
+            y: to_unit(nested_uniform_scramble(sobol_second(index), seed_y))
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::sampler::{Sampler, ONE_MINUS_EPSILON, hash_to_f64, permutation_element};
+use crate::util::*;
+use glam::DVec2;
+
+// jittered samples, each dimension of a pixel is split into one stratum
+// per sample and the strata are visited in a different order per dimension
+#[derive(Clone)]
+pub struct StratifiedSampler {
+    m_samples: u32,
+    m_x_strata: u32,    // 2d strata are laid out on an m_x_strata by m_y_strata grid
+    m_y_strata: u32,
+    m_seed: u64,
+    m_pixel_hash: u64,
+    m_index: u32,
+    m_dimension: u64
+}
+
+impl StratifiedSampler {
+    pub fn new(samples: u32, seed: u64) -> StratifiedSampler {
+        let x_strata = (samples as f64).sqrt() as u32;
+        let y_strata = samples / x_strata;
+        StratifiedSampler {
+            m_samples: samples,
+            m_x_strata: x_strata,
+            m_y_strata: y_strata,
+            m_seed: seed,
+            m_pixel_hash: 0,
+            m_index: 0,
+            m_dimension: 0
+        }
+    }
+
+    // hash of the current pixel, dimension and salt
+    fn hash(&self, salt: u64) -> u64 {
+        hash_u64(self.m_pixel_hash ^ hash_u64(self.m_dimension.wrapping_mul(4).wrapping_add(salt)))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.m_pixel_hash = pixel_seed(self.m_seed, x, y);
+        self.m_index = index;
+        self.m_dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let n = self.m_samples;
+        let stratum = permutation_element(self.m_index % n, n, self.hash(0) as u32);
+        let jitter = hash_to_f64(hash_u64(self.hash(1) ^ self.m_index as u64));
+        self.m_dimension += 1;
+
+        ((stratum as f64 + jitter) / n as f64).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> DVec2 {
+        // samples beyond the full grid start over in a new order
+        let (nx, ny) = (self.m_x_strata, self.m_y_strata);
+        let stratum = permutation_element(self.m_index % (nx * ny), nx * ny, self.hash(0) as u32);
+        let jitter = hash_u64(self.hash(1) ^ self.m_index as u64);
+        self.m_dimension += 1;
+
+        DVec2 {
+            x: (((stratum % nx) as f64 + hash_to_f64(jitter)) / nx as f64).min(ONE_MINUS_EPSILON),
+            y: (((stratum / nx) as f64 + hash_to_f64(hash_u64(jitter))) / ny as f64).min(ONE_MINUS_EPSILON)
+        }
+    }
+}
@@ -2,38 +2,116 @@ use serde_json::{Value};
 use crate::util::transform::Transform;
 use crate::util::ray::Ray;
 use crate::util::*;
-use glam::f64::DVec3;
+use glam::f64::{DVec2, DVec3};
+
+// mapping from image coordinates to camera space rays, cameras look down -z
+pub enum Projection {
+    PERSPECTIVE,        // pinhole or thin lens
+    ORTHOGRAPHIC,       // parallel rays over an image plane of size m_size
+    FISHEYE(f64),       // equidistant fisheye with the given field of view in radians
+    EQUIRECTANGULAR     // full sphere in latitude-longitude layout
+}
 
 pub struct Camera {
+    pub m_projection: Projection,
     pub m_xform: Transform,
     pub m_size: Vec<f64>,         // physical size of image plane
     pub m_focal_distance: f64,    // distance to image plane along z axis
     pub m_resolution: Vec<i64>,   // image resolution
-    pub m_aperture_radius: f64    // size of aperture for depth of field
+    pub m_aperture_radius: f64,   // size of aperture for depth of field
+    pub m_focus_distance: f64,    // distance to the plane in perfect focus
+    pub m_blades: u32,            // number of aperture blades, 0 for a round aperture
+    pub m_blade_rotation: f64     // angle of the first blade corner in radians
 }
 
 impl Camera {
     // generate ray going through image-plane location (u,v)
-    pub fn generate_ray(&self, u: f64, v: f64) -> Ray {
+    // lens is a random sample in [0, 1)^2 picking the point on the aperture
+    // returns None for locations the projection does not cover
+    pub fn generate_ray(&self, u: f64, v: f64, lens: DVec2) -> Option<Ray> {
         let u_phys = u / (self.m_resolution[0] as f64);
         let v_phys = v / (self.m_resolution[1] as f64);
 
+        let o = DVec3 { x: 0.0, y: 0.0, z: 0.0 };
+        match self.m_projection {
+            Projection::PERSPECTIVE => Some(self.perspective_ray(u_phys, v_phys, lens)),
+            Projection::ORTHOGRAPHIC => {
+                let o = DVec3 {
+                    x: (u_phys - 0.5) * self.m_size[0],
+                    y: (0.5 - v_phys) * self.m_size[1],
+                    z: 0.0
+                };
+                Some(self.m_xform.ray(&Ray::new(o, DVec3::NEG_Z, None, None)))
+            },
+            Projection::FISHEYE(fov) => {
+                // image circle fits the shorter side of the image
+                let aspect = self.m_resolution[0] as f64 / self.m_resolution[1] as f64;
+                let (sx, sy) = if aspect >= 1.0 { (aspect, 1.0) } else { (1.0, 1.0 / aspect) };
+                let x = (2.0 * u_phys - 1.0) * sx;
+                let y = (1.0 - 2.0 * v_phys) * sy;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+
+                // angle from the view axis grows linearly with the image radius
+                let theta = r * fov / 2.0;
+                let phi = y.atan2(x);
+                let d = DVec3 {
+                    x: theta.sin() * phi.cos(),
+                    y: theta.sin() * phi.sin(),
+                    z: -theta.cos()
+                };
+                Some(self.m_xform.ray(&Ray::new(o, d, None, None)))
+            },
+            Projection::EQUIRECTANGULAR => {
+                // longitude zero is the view axis, latitude runs from top to bottom
+                let phi = (u_phys - 0.5) * 2.0 * M_PI;
+                let theta = v_phys * M_PI;
+                let d = DVec3 {
+                    x: theta.sin() * phi.sin(),
+                    y: theta.cos(),
+                    z: -theta.sin() * phi.cos()
+                };
+                Some(self.m_xform.ray(&Ray::new(o, d, None, None)))
+            }
+        }
+    }
+
+    fn perspective_ray(&self, u_phys: f64, v_phys: f64, lens: DVec2) -> Ray {
         let o = DVec3 { x: 0.0, y: 0.0, z: 0.0 };
         let d = DVec3 {
             x: (u_phys - 0.5) * self.m_size[0],
             y: (0.5 - v_phys) * self.m_size[1],
-            z: -1.0 * self.m_focal_distance
+            z: -self.m_focal_distance
         };
 
-        return self.m_xform.ray(&Ray::new(o, d, None, None));
+        if self.m_aperture_radius <= 0.0 {
+            return self.m_xform.ray(&Ray::new(o, d, None, None));
+        }
+
+        // thin lens, rays from all over the aperture meet the pinhole ray
+        // on the plane of focus
+        let focus_point = d * (self.m_focus_distance / self.m_focal_distance);
+        let lens_point = if self.m_blades >= 3 {
+            sample_polygon(self.m_blades, self.m_blade_rotation, lens.x, lens.y)
+        } else {
+            sample_disk(lens.x, lens.y)
+        } * self.m_aperture_radius;
+
+        let o = DVec3 { x: lens_point.x, y: lens_point.y, z: 0.0 };
+        return self.m_xform.ray(&Ray::new(o, focus_point - o, None, None));
     }
 
-    pub fn parse_from_json(j: &Value) -> Camera {
-        let j = j.as_object().unwrap();
+    pub fn parse_from_json(j: &Value) -> Result<Camera, String> {
+        let j = match j.as_object() {
+            Some(j) => j,
+            None => return Err(String::from("missing camera block"))
+        };
 
         // get transform
         let camera_transform = match j.get("transform") {
-            Some(value) => Transform::from_json(value),
+            Some(value) => Transform::from_json(value)?,
             None => Transform::identity()
         };
 
@@ -52,27 +130,75 @@ impl Camera {
             },
             None => vec![512, 512]
         };
+        if res.len() < 2 || res[0] < 1 || res[1] < 1 || res[0] > u32::MAX as i64 || res[1] > u32::MAX as i64 {
+            return Err(format!("invalid camera resolution {:?}", res));
+        }
 
         let aperture = match j.get("aperture") {
             Some(value) => safe_value_to_f64(value, 0.0),
             None => 0.0
         };
 
-        // calcuate size of image plane from vfov
+        // plane of focus defaults to the image plane distance
+        let focus_distance = match j.get("focus_distance") {
+            Some(value) => safe_value_to_f64(value, fdist),
+            None => fdist
+        };
+
+        let blades = match j.get("blades") {
+            Some(value) => safe_value_to_i64(value, 0).max(0) as u32,
+            None => 0
+        };
+
+        let blade_rotation = deg_2_rad(match j.get("blade_rotation") {
+            Some(value) => safe_value_to_f64(value, 0.0),
+            None => 0.0
+        });
+
+        let camera_type = match j.get("type") {
+            Some(value) => value.as_str().unwrap_or("perspective"),
+            None => "perspective"
+        };
+        let projection = match camera_type {
+            "perspective" => Projection::PERSPECTIVE,
+            "orthographic" => Projection::ORTHOGRAPHIC,
+            "fisheye" => Projection::FISHEYE(deg_2_rad(match j.get("fov") {
+                Some(value) => safe_value_to_f64(value, 180.0),
+                None => 180.0
+            })),
+            "equirectangular" | "latlong" => Projection::EQUIRECTANGULAR,
+            _ => return Err(format!("could not parse camera type \"{}\"", camera_type))
+        };
+
+        // calcuate size of image plane from vfov, or from the view width for
+        // orthographic cameras
         let vfov = deg_2_rad(match j.get("vfov") {
             Some(value) => safe_value_to_f64(value, 90.0),
             None => 90.0
         });
 
-        let size_y = 2.0 * (vfov / 2.0).tan() * fdist;
+        let size_y = match projection {
+            Projection::ORTHOGRAPHIC => {
+                let width = match j.get("width") {
+                    Some(value) => safe_value_to_f64(value, 2.0),
+                    None => 2.0
+                };
+                width * res[1] as f64 / res[0] as f64
+            },
+            _ => 2.0 * (vfov / 2.0).tan() * fdist
+        };
         let size_x = (res[0] as f64 / res[1] as f64) * size_y;
 
-        Camera {
+        Ok(Camera {
+            m_projection: projection,
             m_xform: camera_transform,
             m_size: vec![size_x, size_y],
             m_focal_distance: fdist,
             m_resolution: res,
-            m_aperture_radius: aperture
-        }
+            m_aperture_radius: aperture,
+            m_focus_distance: focus_distance,
+            m_blades: blades,
+            m_blade_rotation: blade_rotation
+        })
     }
 }
\ No newline at end of file
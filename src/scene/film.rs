@@ -0,0 +1,160 @@
+use serde_json::{Value};
+use crate::util::*;
+use crate::util::color::Color;
+use image::{Rgb, Rgb32FImage};
+
+// pixel reconstruction filter shape
+enum FilterType {
+    BOX,
+    TENT,
+    GAUSSIAN(f64),          // standard deviation
+    MITCHELL(f64, f64),     // b and c parameters
+    LANCZOS                 // sinc windowed by a sinc stretched to the radius
+}
+
+// separable filter weighting samples by their distance to a pixel center
+pub struct Filter {
+    m_type: FilterType,
+    pub m_radius: f64
+}
+
+impl Filter {
+    // parse from the "filter" entry of the film or output block, a box over
+    // the pixel if missing
+    pub fn from_json(j: &Value) -> Result<Filter, String> {
+        let filter_type = j["type"].as_str().unwrap_or("box");
+
+        let (filter, default_radius) = match filter_type {
+            "box" => (FilterType::BOX, 0.5),
+            "tent" => (FilterType::TENT, 1.0),
+            "gaussian" => (FilterType::GAUSSIAN(safe_value_to_f64(&j["sigma"], 0.5)), 1.5),
+            "mitchell" => (FilterType::MITCHELL(
+                safe_value_to_f64(&j["b"], 1.0 / 3.0),
+                safe_value_to_f64(&j["c"], 1.0 / 3.0)
+            ), 2.0),
+            "lanczos" => (FilterType::LANCZOS, 2.0),
+            _ => return Err(format!("could not parse filter \"{}\"", filter_type))
+        };
+
+        let radius = safe_value_to_f64(&j["radius"], default_radius);
+        if radius <= 0.0 {
+            return Err(String::from("filter radius must be positive"));
+        }
+
+        Ok(Filter { m_type: filter, m_radius: radius })
+    }
+
+    // one dimensional weight at offset x from the pixel center
+    fn eval_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x >= self.m_radius {
+            return 0.0;
+        }
+        let r = self.m_radius;
+
+        match self.m_type {
+            FilterType::BOX => 1.0,
+            FilterType::TENT => 1.0 - x / r,
+            FilterType::GAUSSIAN(sigma) => {
+                // shifted so the weight reaches zero at the radius
+                let g = |v: f64| (-v * v / (2.0 * sigma * sigma)).exp();
+                (g(x) - g(r)).max(0.0)
+            },
+            FilterType::MITCHELL(b, c) => {
+                // defined over [-2, 2], scaled to the radius
+                let x = 2.0 * x / r;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)) / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c)) / 6.0
+                }
+            },
+            FilterType::LANCZOS => {
+                let sinc = |v: f64| if v < 1e-5 { 1.0 } else { (M_PI * v).sin() / (M_PI * v) };
+                sinc(x) * sinc(x / r)
+            }
+        }
+    }
+
+    pub fn eval(&self, dx: f64, dy: f64) -> f64 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+}
+
+// weighted sums of samples over a block of pixels, which may reach past the image
+// pixel (x, y) covers [x, x + 1) x [y, y + 1) in image coordinates
+pub struct Film {
+    pub m_x: i64,
+    pub m_y: i64,
+    pub m_width: u32,
+    pub m_height: u32,
+    m_sums: Vec<Color>,
+    m_weights: Vec<f64>
+}
+
+impl Film {
+    pub fn new(x: i64, y: i64, width: u32, height: u32) -> Film {
+        let n = (width * height) as usize;
+        Film { m_x: x, m_y: y, m_width: width, m_height: height, m_sums: vec![Color::BLACK; n], m_weights: vec![0.0; n] }
+    }
+
+    // film for a block of pixels, grown by the pixels the filter reaches into
+    pub fn for_block(x: u32, y: u32, width: u32, height: u32, filter: &Filter) -> Film {
+        let pad = (filter.m_radius - 0.5).ceil().max(0.0) as u32;
+        Film::new(x as i64 - pad as i64, y as i64 - pad as i64, width + 2 * pad, height + 2 * pad)
+    }
+
+    // add a sample at image position (px, py) to all pixels within the filter radius
+    pub fn add_sample(&mut self, filter: &Filter, px: f64, py: f64, c: Color) {
+        let r = filter.m_radius;
+        let x0 = ((px - 0.5 - r).ceil() as i64).max(self.m_x);
+        let x1 = ((px - 0.5 + r).floor() as i64).min(self.m_x + self.m_width as i64 - 1);
+        let y0 = ((py - 0.5 - r).ceil() as i64).max(self.m_y);
+        let y1 = ((py - 0.5 + r).floor() as i64).min(self.m_y + self.m_height as i64 - 1);
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let w = filter.eval(x as f64 + 0.5 - px, y as f64 + 0.5 - py);
+                if w == 0.0 {
+                    continue;
+                }
+                let k = ((y - self.m_y) * self.m_width as i64 + (x - self.m_x)) as usize;
+                self.m_sums[k] += c * w;
+                self.m_weights[k] += w;
+            }
+        }
+    }
+
+    // add the sums of another film where the two overlap
+    pub fn merge(&mut self, other: &Film) {
+        let x0 = self.m_x.max(other.m_x);
+        let x1 = (self.m_x + self.m_width as i64).min(other.m_x + other.m_width as i64);
+        let y0 = self.m_y.max(other.m_y);
+        let y1 = (self.m_y + self.m_height as i64).min(other.m_y + other.m_height as i64);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let k = ((y - self.m_y) * self.m_width as i64 + (x - self.m_x)) as usize;
+                let j = ((y - other.m_y) * other.m_width as i64 + (x - other.m_x)) as usize;
+                self.m_sums[k] += other.m_sums[j];
+                self.m_weights[k] += other.m_weights[j];
+            }
+        }
+    }
+
+    // normalized pixel values, pixels without any weight are black
+    pub fn to_image(&self) -> Rgb32FImage {
+        let mut image = Rgb32FImage::new(self.m_width, self.m_height);
+        for (k, (sum, weight)) in self.m_sums.iter().zip(self.m_weights.iter()).enumerate() {
+            let c = if *weight != 0.0 { *sum / *weight } else { Color::BLACK };
+            let k = k as u32;
+            image.put_pixel(k % self.m_width, k / self.m_width, Rgb([c.r as f32, c.g as f32, c.b as f32]));
+        }
+        image
+    }
+}
@@ -1,106 +1,226 @@
 mod camera;
+mod film;
 
 use camera::*;
+use film::{Film, Filter};
 use serde_json::{Value};
-use crate::material::Material;
+use crate::integrator::{Integrator, ObjectIntegrator};
+use crate::sampler::{Sampler, ObjectSampler};
 use crate::surfaces::{SurfaceGroup, SurfaceBase};
 use crate::util::*;
-use image::{RgbImage};
-use rand::prelude::*;
+use image::{Rgb, Rgb32FImage};
+use crate::util::color::Color;
+use crate::output::{self, OutputSettings, RenderedImage};
 use crate::util::ray::{Ray, HitInfo};
-use glam::DVec3;
+use crate::output::tonemap::ToneMapper;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// edge length of the square blocks of pixels handed to render threads
+const TILE_SIZE: u32 = 16;
+
+// block of pixels rendered by a single thread
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32
+}
 
 pub struct Scene {
     m_camera: Camera,
-    m_surface_group: SurfaceGroup,
+    pub m_surface_group: SurfaceGroup,
+    m_integrator: ObjectIntegrator,
+    m_filter: Filter,
+    m_sampler: ObjectSampler,   // cloned by every render thread
     pub m_image_samples: i64,
-    pub m_background: image::Rgb<u8>
+    pub m_background: Color,
+    pub m_threads: i64,         // number of render threads, 0 for all cores
+    pub m_tonemapper: ToneMapper,
+    pub m_output: OutputSettings
 }
 
 impl Scene {
-    pub fn parse_from_json(j: &Value) -> Scene {
+    pub fn parse_from_json(j: &Value) -> Result<Scene, String> {
+
+        let mut surface_group = SurfaceGroup::from_json(&j["surfaces"])?;
+        surface_group.build_accelerator(&j["accelerator"])?;
 
-        Scene { 
-            m_camera: Camera::parse_from_json(&j["camera"]),
-            m_surface_group: SurfaceGroup::from_json(&j["surfaces"]),
-            m_image_samples: safe_value_to_i64(&j["image_samples"], 1),
-            m_background: safe_value_to_color(&j["background"], image::Rgb([0, 0, 0]))
+        let image_samples = safe_value_to_i64(&j["image_samples"], 1);
+        if image_samples < 1 {
+            return Err(format!("image_samples must be at least 1, got {}", image_samples));
         }
+
+        // all random numbers are derived from the seed, pixel and sample index,
+        // so a seed reproduces the image for any thread count
+        let seed = match j.get("seed") {
+            Some(v) => match v.as_u64() {
+                Some(s) => s,
+                None => return Err(format!("seed must be a non-negative integer, got {}", v))
+            },
+            None => 0
+        };
+        let sampler = ObjectSampler::from_json(&j["sampler"], image_samples, seed)?;
+
+        Ok(Scene { 
+            m_camera: Camera::parse_from_json(&j["camera"])?,
+            m_surface_group: surface_group,
+            m_integrator: ObjectIntegrator::from_json(&j["integrator"])?,
+            m_filter: Filter::from_json(&output::settings_block(j)["filter"])?,
+            m_sampler: sampler,
+            m_image_samples: image_samples,
+            m_background: Color::from_json(&j["background"], Color::BLACK)?,
+            m_threads: safe_value_to_i64(&j["threads"], 0),
+            m_tonemapper: ToneMapper::from_json(j)?,
+            m_output: OutputSettings::from_json(j)?
+        })
     }
 
-    fn ray_trace_color(&self, ray: &mut Ray, depth: i32) -> image::Rgb<u8> {
-        let mut hit = HitInfo::new();
-
-        if self.m_surface_group.intersect(ray, &mut hit) {
-            // get emitted color
-            let emitted = hit.mat.as_ref().emitted(ray, &hit);
-
-            if depth < crate::util::MAX_RAYTRACE_DEPTH {
-                // get scattered ray
-                let mut scattered = Ray::new(
-                    DVec3::ZERO, 
-                    DVec3::ZERO, 
-                    None, None);
-
-                // get attenuation
-                let attenuation = hit.mat.as_ref().scatter(ray, &hit, &mut scattered);
-
-                // call recursivley
-                match attenuation {
-                    Some(v) => return
-                    add_color(
-                        emitted,
-                        compose_color(v,  self.ray_trace_color(&mut scattered, depth + 1))
-                    ),
-                    None => return emitted
+    // render a rectangular block of pixels, radiance samples are splatted into a
+    // film reaching past the tile by the filter radius, aovs are averaged per
+    // pixel and returned in row-major order
+    fn render_tile(&self, tile: &Tile) -> (Film, Vec<Vec<Color>>) {
+        let aovs = &self.m_output.aovs;
+        let mut film = Film::for_block(tile.x, tile.y, tile.width, tile.height, &self.m_filter);
+        let mut buffers = vec![Vec::with_capacity((tile.width * tile.height) as usize); aovs.len()];
+        let mut sampler = self.m_sampler.clone();
+
+        for y in tile.y..tile.y + tile.height {
+            for x in tile.x..tile.x + tile.width {
+
+                let mut acc_aovs = vec![Color::BLACK; aovs.len()];
+
+                // for each sample
+                for i in 0..self.m_image_samples {
+                    // samples depend only on the pixel and sample index, not
+                    // on which thread renders them
+                    sampler.start_pixel_sample(x, y, i as u32);
+                    let sample = sampler.next_2d();
+                    let lens = sampler.next_2d();
+                    let (px, py) = ((x as f64) + sample.x, (y as f64) + sample.y);
+
+                    // locations outside the projection still count as black samples
+                    let ray = match self.m_camera.generate_ray(px, py, lens) {
+                        Some(r) => r,
+                        None => {
+                            film.add_sample(&self.m_filter, px, py, Color::BLACK);
+                            continue;
+                        }
+                    };
+
+                    let sample_color = self.m_integrator.li(self, &ray, &mut sampler);
+
+                    film.add_sample(&self.m_filter, px, py, sample_color);
+
+                    // aovs come from a separate primary hit so they do not
+                    // depend on what the integrator does with the ray, this
+                    // costs one extra intersection per sample when aovs are on
+                    if !aovs.is_empty() {
+                        let mut aov_ray = Ray::new(ray.o, ray.d, Some(ray.mint), Some(ray.maxt));
+                        let mut hit = HitInfo::new();
+                        let hit = if self.m_surface_group.intersect(&mut aov_ray, &mut hit) { Some(&hit) } else { None };
+                        for (acc, aov) in acc_aovs.iter_mut().zip(aovs.iter()) {
+                            if aov.is_averaged() {
+                                *acc += aov.value(&aov_ray, hit);
+                            } else if i == 0 {
+                                *acc = aov.value(&aov_ray, hit);
+                            }
+                        }
+                    }
+                }
+
+                for (k, aov) in aovs.iter().enumerate() {
+                    let v = if aov.is_averaged() { acc_aovs[k] / self.m_image_samples as f64 } else { acc_aovs[k] };
+                    buffers[k].push(v);
                 }
             }
-            return emitted;
-        } else {
-            return self.m_background;
         }
+
+        return (film, buffers);
     }
 
-    pub fn ray_trace_image(&self) -> RgbImage {
+    // number of render threads, 0 means one per available core
+    fn thread_count(&self) -> usize {
+        if self.m_threads > 0 {
+            return self.m_threads as usize;
+        }
+        match std::thread::available_parallelism() {
+            Ok(n) => n.get(),
+            Err(_) => 1
+        }
+    }
+
+    // render the scene into a linear floating point image and its aovs
+    pub fn ray_trace_image(&self) -> RenderedImage {
         // allocate image
         let width = u32::try_from(self.m_camera.m_resolution[0]).unwrap();
         let height = u32::try_from(self.m_camera.m_resolution[1]).unwrap();
 
-        let mut image = RgbImage::new(width, height);
-
-        // for each pixel
-        for i in 0..height {
-            for j in 0..width {
-
-                // init pixel color
-                *image.get_pixel_mut(i, j) = image::Rgb([0, 0, 0]);
-
-                let mut acc_color:[i64; 3] = [0, 0, 0];
+        let mut film = Film::new(0, 0, width, height);
+        let mut images = vec![Rgb32FImage::new(width, height); self.m_output.aovs.len()];
+
+        // split image into tiles
+        let mut tiles: Vec<Tile> = Vec::new();
+        for y in (0..height).step_by(TILE_SIZE as usize) {
+            for x in (0..width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: TILE_SIZE.min(width - x),
+                    height: TILE_SIZE.min(height - y)
+                });
+            }
+        }
 
-                // for each sample
-                for s in 0..self.m_image_samples {
-                    let sample = vec![random::<f64>(), random::<f64>()];
-                    let mut ray = self.m_camera.generate_ray(
-                        (i as f64) + sample[0],
-                        (j as f64) + sample[1]
-                    );
-
-                    let sample_color = self.ray_trace_color(&mut ray, 0);
-
-                    acc_color[0] += sample_color[0] as i64;
-                    acc_color[1] += sample_color[1] as i64;
-                    acc_color[2] += sample_color[2] as i64;
+        // worker threads pull tiles from a shared counter until none are left
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let n_threads = self.thread_count().min(tiles.len().max(1));
+
+        thread::scope(|s| {
+            for _ in 0..n_threads {
+                let sender = sender.clone();
+                let tiles = &tiles;
+                let next_tile = &next_tile;
+                s.spawn(move || {
+                    loop {
+                        let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if index >= tiles.len() {
+                            break;
+                        }
+                        sender.send((index, self.render_tile(&tiles[index]))).unwrap();
+                    }
+                });
+            }
+            drop(sender);
+
+            // copy finished tiles into the image as they arrive, films are kept
+            // to be merged in a fixed order as neighbouring tiles overlap
+            let mut tile_films: Vec<Option<Film>> = (0..tiles.len()).map(|_| None).collect();
+            for (index, (tile_film, buffers)) in receiver.iter() {
+                tile_films[index] = Some(tile_film);
+                let tile = &tiles[index];
+                for (image, pixels) in images.iter_mut().zip(buffers) {
+                    for (k, pixel) in pixels.into_iter().enumerate() {
+                        let k = k as u32;
+                        image.put_pixel(
+                            tile.x + k % tile.width,
+                            tile.y + k / tile.width,
+                            Rgb([pixel.r as f32, pixel.g as f32, pixel.b as f32])
+                        );
+                    }
                 }
-
-                *image.get_pixel_mut(i, j) = image::Rgb([
-                    (acc_color[0] / self.m_image_samples).try_into().unwrap(),
-                    (acc_color[1] / self.m_image_samples).try_into().unwrap(),
-                    (acc_color[2] / self.m_image_samples).try_into().unwrap()
-                ]);
             }
-        };
+            for tile_film in tile_films.iter().flatten() {
+                film.merge(tile_film);
+            }
+        });
 
-        return image;
+        return RenderedImage {
+            radiance: film.to_image(),
+            aovs: self.m_output.aovs.iter().copied().zip(images).collect()
+        };
 
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,343 @@
+use crate::surfaces::{Surface, SurfaceBase};
+use crate::util::*;
+use crate::util::bbox::BBox;
+use crate::util::ray::{Ray, HitInfo};
+use glam::DVec3;
+use serde_json::{Value};
+use std::fmt;
+
+// relative cost of visiting an interior node vs intersecting a surface
+const SAH_TRAVERSAL_COST: f64 = 0.125;
+
+// strategy used to partition surfaces at each node
+#[derive(Clone, Copy, PartialEq)]
+pub enum SplitMethod {
+    SAH,            // surface area heuristic over bucketed centroids
+    MIDDLE,         // midpoint of the centroid bounds
+    EQUALCOUNTS     // median centroid, equal number of surfaces on each side
+}
+
+pub struct BvhSettings {
+    pub split: SplitMethod,
+    pub max_leaf_size: usize,
+    pub buckets: usize
+}
+
+impl BvhSettings {
+    pub fn from_json(j: &Value) -> Result<BvhSettings, String> {
+        let split = match j.get("split").and_then(|v| v.as_str()) {
+            Some("middle") => SplitMethod::MIDDLE,
+            Some("equal_counts") => SplitMethod::EQUALCOUNTS,
+            Some("sah") | None => SplitMethod::SAH,
+            Some(other) => return Err(format!("unknown bvh split method \"{}\"", other))
+        };
+
+        let max_leaf_size = match j.get("max_leaf_size") {
+            Some(v) => safe_value_to_i64(v, 4),
+            None => 4
+        };
+
+        let buckets = match j.get("buckets") {
+            Some(v) => safe_value_to_i64(v, 12),
+            None => 12
+        };
+
+        Ok(BvhSettings {
+            split,
+            max_leaf_size: max_leaf_size.max(1) as usize,
+            buckets: buckets.max(2) as usize
+        })
+    }
+}
+
+// statistics gathered while building
+#[derive(Clone)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub surface_count: usize
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let avg_leaf = if self.leaf_count > 0 {
+            self.surface_count as f64 / self.leaf_count as f64
+        } else {
+            0.0
+        };
+        write!(f, "bvh: {} surfaces, {} nodes, {} leaves, depth {}, leaf size min {} / avg {:.2} / max {}",
+            self.surface_count, self.node_count, self.leaf_count, self.max_depth,
+            self.min_leaf_size, avg_leaf, self.max_leaf_size)
+    }
+}
+
+// node of the flattened hierarchy
+// leaves reference a range of m_surfaces, interior nodes store the index of
+// their second child (the first child always follows the parent directly)
+struct BvhNode {
+    bbox: BBox,
+    offset: usize,   // first surface for leaves, second child for interior nodes
+    count: usize,    // number of surfaces, 0 for interior nodes
+    axis: usize      // split axis for interior nodes
+}
+
+// bounding volume hierarchy over a list of surfaces
+pub struct Bvh {
+    m_surfaces: Vec<Surface>,
+    m_nodes: Vec<BvhNode>,
+    pub m_stats: BvhStats
+}
+
+// per surface data used during construction
+struct BuildItem {
+    index: usize,
+    bbox: BBox,
+    centroid: DVec3
+}
+
+impl SurfaceBase for Bvh {
+    fn intersect(&self, ray: &mut Ray, hit: &mut HitInfo) -> bool {
+        if self.m_nodes.is_empty() {
+            return false;
+        }
+
+        let inv_d = DVec3::ONE / ray.d;
+        let dir_neg = [inv_d.x < 0.0, inv_d.y < 0.0, inv_d.z < 0.0];
+
+        let mut hit_something = false;
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.m_nodes[node_index];
+            if !node.bbox.intersect(ray, inv_d) {
+                continue;
+            }
+
+            if node.count > 0 {
+                for surface in self.m_surfaces[node.offset..node.offset + node.count].iter() {
+                    if surface.intersect(ray, hit) {
+                        hit_something = true;
+                        ray.maxt = hit.t;
+                    }
+                }
+            } else if dir_neg[node.axis] {
+                // visit the child closer to the ray origin first
+                stack.push(node_index + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+
+        return hit_something;
+    }
+
+    fn bounds(&self) -> BBox {
+        match self.m_nodes.first() {
+            Some(n) => n.bbox,
+            None => BBox::empty()
+        }
+    }
+}
+
+impl Bvh {
+    // build hierarchy, taking ownership of the surfaces
+    pub fn new(surfaces: Vec<Surface>, settings: &BvhSettings) -> Bvh {
+        let mut items: Vec<BuildItem> = surfaces.iter().enumerate().map(|(i, s)| {
+            let bbox = s.bounds();
+            BuildItem { index: i, bbox, centroid: bbox.center() }
+        }).collect();
+
+        let mut stats = BvhStats {
+            node_count: 0,
+            leaf_count: 0,
+            max_depth: 0,
+            min_leaf_size: if items.is_empty() { 0 } else { usize::MAX },
+            max_leaf_size: 0,
+            surface_count: items.len()
+        };
+
+        let mut nodes: Vec<BvhNode> = Vec::with_capacity(2 * items.len());
+        if !items.is_empty() {
+            Bvh::build_recursive(&mut items, 0, 0, settings, &mut nodes, &mut stats);
+        }
+        stats.node_count = nodes.len();
+
+        // reorder surfaces so that every leaf references a contiguous range
+        let mut slots: Vec<Option<Surface>> = surfaces.into_iter().map(Some).collect();
+        let ordered = items.iter().map(|item| slots[item.index].take().unwrap()).collect();
+
+        Bvh { m_surfaces: ordered, m_nodes: nodes, m_stats: stats }
+    }
+
+    fn build_recursive(
+        items: &mut [BuildItem],
+        offset: usize,
+        depth: usize,
+        settings: &BvhSettings,
+        nodes: &mut Vec<BvhNode>,
+        stats: &mut BvhStats
+    ) -> usize {
+        let mut bbox = BBox::empty();
+        let mut centroid_bbox = BBox::empty();
+        for item in items.iter() {
+            bbox.enclose(&item.bbox);
+            centroid_bbox.enclose_point(item.centroid);
+        }
+
+        let node_index = nodes.len();
+        nodes.push(BvhNode { bbox, offset, count: items.len(), axis: 0 });
+        stats.max_depth = stats.max_depth.max(depth);
+
+        let axis = centroid_bbox.max_extent();
+        let extent = centroid_bbox.max[axis] - centroid_bbox.min[axis];
+
+        // all centroids coinciding means no split can separate them
+        let mid = if items.len() <= 1 || extent <= 0.0 {
+            None
+        } else {
+            match settings.split {
+                SplitMethod::MIDDLE => split_middle(items, axis, &centroid_bbox, settings),
+                SplitMethod::EQUALCOUNTS => split_equal_counts(items, axis, settings),
+                SplitMethod::SAH => split_sah(items, axis, &bbox, &centroid_bbox, settings)
+            }
+        };
+
+        let mid = match mid {
+            Some(m) => m,
+            None => {
+                stats.leaf_count += 1;
+                stats.min_leaf_size = stats.min_leaf_size.min(items.len());
+                stats.max_leaf_size = stats.max_leaf_size.max(items.len());
+                return node_index;
+            }
+        };
+
+        let (left, right) = items.split_at_mut(mid);
+        Bvh::build_recursive(left, offset, depth + 1, settings, nodes, stats);
+        let second = Bvh::build_recursive(right, offset + mid, depth + 1, settings, nodes, stats);
+
+        nodes[node_index].count = 0;
+        nodes[node_index].offset = second;
+        nodes[node_index].axis = axis;
+
+        return node_index;
+    }
+}
+
+// split at the middle of the centroid bounds
+// returns the index of the first item of the second child, or None for a leaf
+fn split_middle(items: &mut [BuildItem], axis: usize, centroid_bbox: &BBox, settings: &BvhSettings) -> Option<usize> {
+    if items.len() <= settings.max_leaf_size {
+        return None;
+    }
+
+    let middle = centroid_bbox.center()[axis];
+    let mid = partition(items, |item| item.centroid[axis] < middle);
+
+    // fall back to equal counts if every item landed on one side
+    if mid == 0 || mid == items.len() {
+        return split_equal_counts(items, axis, settings);
+    }
+    Some(mid)
+}
+
+// split at the median centroid
+fn split_equal_counts(items: &mut [BuildItem], axis: usize, settings: &BvhSettings) -> Option<usize> {
+    if items.len() <= settings.max_leaf_size {
+        return None;
+    }
+
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    Some(mid)
+}
+
+// split minimizing the surface area heuristic over evenly spaced buckets
+fn split_sah(items: &mut [BuildItem], axis: usize, bbox: &BBox, centroid_bbox: &BBox, settings: &BvhSettings) -> Option<usize> {
+    // too few items for buckets to be meaningful
+    if items.len() <= 2 {
+        return split_equal_counts(items, axis, settings);
+    }
+
+    let n_buckets = settings.buckets;
+    let c_min = centroid_bbox.min[axis];
+    let c_extent = centroid_bbox.max[axis] - c_min;
+    let bucket_of = |item: &BuildItem| -> usize {
+        let b = (n_buckets as f64 * (item.centroid[axis] - c_min) / c_extent) as usize;
+        b.min(n_buckets - 1)
+    };
+
+    let mut counts = vec![0usize; n_buckets];
+    let mut bounds = vec![BBox::empty(); n_buckets];
+    for item in items.iter() {
+        let b = bucket_of(item);
+        counts[b] += 1;
+        bounds[b].enclose(&item.bbox);
+    }
+
+    // sweep from the right to get the area and count of every suffix
+    let mut right_area = vec![0.0; n_buckets];
+    let mut right_count = vec![0usize; n_buckets];
+    let mut acc_box = BBox::empty();
+    let mut acc_count = 0;
+    for b in (1..n_buckets).rev() {
+        acc_box.enclose(&bounds[b]);
+        acc_count += counts[b];
+        right_area[b] = acc_box.surface_area();
+        right_count[b] = acc_count;
+    }
+
+    // sweep from the left, evaluating a split after every bucket
+    let mut best_cost = f64::INFINITY;
+    let mut best_split = 0;
+    acc_box = BBox::empty();
+    acc_count = 0;
+    for b in 0..n_buckets - 1 {
+        acc_box.enclose(&bounds[b]);
+        acc_count += counts[b];
+        if acc_count == 0 || right_count[b + 1] == 0 {
+            continue;
+        }
+        let cost = acc_count as f64 * acc_box.surface_area() + right_count[b + 1] as f64 * right_area[b + 1];
+        if cost < best_cost {
+            best_cost = cost;
+            best_split = b;
+        }
+    }
+
+    let parent_area = bbox.surface_area();
+    let split_cost = if parent_area > 0.0 {
+        SAH_TRAVERSAL_COST + best_cost / parent_area
+    } else {
+        f64::INFINITY
+    };
+    let leaf_cost = items.len() as f64;
+
+    if items.len() <= settings.max_leaf_size && leaf_cost <= split_cost {
+        return None;
+    }
+    if best_cost == f64::INFINITY {
+        return split_equal_counts(items, axis, settings);
+    }
+
+    let mid = partition(items, |item| bucket_of(item) <= best_split);
+    Some(mid)
+}
+
+// move items satisfying pred to the front, return the number of such items
+fn partition<F: Fn(&BuildItem) -> bool>(items: &mut [BuildItem], pred: F) -> usize {
+    let mut first = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
@@ -0,0 +1,265 @@
+use crate::util::*;
+use serde_json::{Value};
+use crate::util::transform::Transform;
+use crate::util::bbox::BBox;
+use crate::surfaces::{SurfaceBase, EmitterRecord, emitter_sample};
+use crate::util::color::Color;
+use crate::surfaces::{obj, ply};
+use crate::material::{ObjectMaterial, Material};
+use glam::{DVec2, DVec3};
+use std::sync::Arc;
+
+// indexed triangle mesh, vertex data is stored in world space
+pub struct Mesh {
+    pub positions: Vec<DVec3>,
+    pub normals: Vec<DVec3>,     // per vertex normals, empty if not present
+    pub uvs: Vec<DVec2>,         // per vertex texture coordinates, empty if not present
+    pub colors: Vec<DVec3>,      // per vertex linear colors, empty if not present
+    pub triangles: Vec<[u32; 3]>,
+    m_material: Arc<ObjectMaterial>,
+    m_id: u32
+}
+
+// single triangle referencing a shared mesh
+#[derive(Clone)]
+pub struct Triangle {
+    m_mesh: Arc<Mesh>,
+    m_index: usize
+}
+
+impl Mesh {
+    // load mesh from file and return one triangle surface per face
+    pub fn from_json(j: &Value, id: u32) -> Result<Vec<Triangle>, String> {
+        let filename = match j.get("filename").and_then(|v| v.as_str()) {
+            Some(v) => v,
+            None => return Err(String::from("can't parse mesh without filename"))
+        };
+
+        let transform = match j.get("transform") {
+            Some(v) => Transform::from_json(v)?,
+            None => Transform::identity()
+        };
+
+        let material = match j.get("material") {
+            Some(v) => ObjectMaterial::from_json(v)?,
+            None => return Err(String::from("can't parse without material"))
+        };
+
+        // optional list of obj groups to load, all groups if missing
+        let groups: Option<Vec<String>> = j.get("groups").and_then(|v| v.as_array()).map(|a| {
+            a.iter().filter_map(|g| g.as_str().map(String::from)).collect()
+        });
+
+        let extension = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+        let loaded = match extension.as_str() {
+            "obj" => obj::load_obj(filename, groups.as_deref()),
+            "ply" => ply::load_ply(filename),
+            _ => return Err(format!("unsupported mesh format \"{}\"", filename))
+        };
+        let mut mesh = match loaded {
+            Ok(m) => m,
+            Err(e) => return Err(format!("error loading mesh \"{}\": {}", filename, e))
+        };
+
+        mesh.m_material = Arc::new(material);
+        mesh.m_id = id;
+        mesh.apply_transform(&transform);
+
+        let mesh = Arc::new(mesh);
+        Ok((0..mesh.triangles.len()).map(|i| Triangle { m_mesh: Arc::clone(&mesh), m_index: i }).collect())
+    }
+
+    pub fn new(
+        positions: Vec<DVec3>,
+        normals: Vec<DVec3>,
+        uvs: Vec<DVec2>,
+        colors: Vec<DVec3>,
+        triangles: Vec<[u32; 3]>
+    ) -> Mesh {
+        Mesh { positions, normals, uvs, colors, triangles, m_material: Arc::new(ObjectMaterial::EMPTY), m_id: 0 }
+    }
+
+    // bring vertex data from object space into world space
+    fn apply_transform(&mut self, xform: &Transform) {
+        for p in self.positions.iter_mut() {
+            *p = xform.point(*p);
+        }
+        for n in self.normals.iter_mut() {
+            // leave missing normals at zero so they fall back to the face normal
+            if *n != DVec3::ZERO {
+                *n = xform.normal(*n);
+            }
+        }
+    }
+}
+
+impl SurfaceBase for Triangle {
+    // watertight ray-triangle intersection (Woop, Benthin and Wald 2013)
+    fn intersect(&self, ray: &mut ray::Ray, hit: &mut ray::HitInfo) -> bool {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        let p0 = mesh.positions[tri[0] as usize];
+        let p1 = mesh.positions[tri[1] as usize];
+        let p2 = mesh.positions[tri[2] as usize];
+
+        // translate vertices to ray origin
+        let mut p0t = p0 - ray.o;
+        let mut p1t = p1 - ray.o;
+        let mut p2t = p2 - ray.o;
+
+        // permute so that the largest direction component is z
+        let kz = max_dimension(ray.d.abs());
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+        let permute = |v: DVec3| DVec3 { x: v[kx], y: v[ky], z: v[kz] };
+        let d = permute(ray.d);
+        p0t = permute(p0t);
+        p1t = permute(p1t);
+        p2t = permute(p2t);
+
+        // shear so the ray direction becomes +z
+        let sx = -d.x / d.z;
+        let sy = -d.y / d.z;
+        let sz = 1.0 / d.z;
+        p0t.x += sx * p0t.z;
+        p0t.y += sy * p0t.z;
+        p1t.x += sx * p1t.z;
+        p1t.y += sy * p1t.z;
+        p2t.x += sx * p2t.z;
+        p2t.y += sy * p2t.z;
+
+        // edge functions
+        let e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+        let e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+        let e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return false;
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return false;
+        }
+
+        // scaled distance, compared against the ray bounds without dividing
+        p0t.z *= sz;
+        p1t.z *= sz;
+        p2t.z *= sz;
+        let t_scaled = e0 * p0t.z + e1 * p1t.z + e2 * p2t.z;
+        if det < 0.0 && (t_scaled > ray.mint * det || t_scaled < ray.maxt * det) {
+            return false;
+        }
+        if det > 0.0 && (t_scaled < ray.mint * det || t_scaled > ray.maxt * det) {
+            return false;
+        }
+
+        let inv_det = 1.0 / det;
+        let b0 = e0 * inv_det;
+        let b1 = e1 * inv_det;
+        let b2 = e2 * inv_det;
+        let t = t_scaled * inv_det;
+
+        let cross = (p1 - p0).cross(p2 - p0);
+        let gn = cross.normalize();
+
+        let sn = self.shading_normal(b0, b1, b2, gn);
+        let uv = self.uv(b0, b1, b2);
+
+        hit.t = t;
+        hit.p = b0 * p0 + b1 * p1 + b2 * p2;
+        hit.sn = sn;
+        // keep geometric normal on the same side as the shading normal
+        hit.gn = if gn.dot(sn) < 0.0 { -gn } else { gn };
+        hit.front_face = ray.d.dot(hit.gn) < 0.0;
+        hit.uv = uv;
+        hit.vertex_color = self.color(b0, b1, b2);
+        hit.pdf_area = 2.0 / cross.length();
+        hit.mat = Arc::clone(&mesh.m_material);
+        hit.object_id = mesh.m_id;
+
+        return true;
+    }
+
+    fn bounds(&self) -> BBox {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        let mut bbox = BBox::empty();
+        for i in tri {
+            bbox.enclose_point(mesh.positions[i as usize]);
+        }
+        bbox.pad(EPSILON * 0.01)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.m_mesh.m_material.is_emissive()
+    }
+
+    // sample uniformly by area
+    fn sample(&self, rec: &mut EmitterRecord, rv: DVec2) -> Color {
+        let (p0, p1, p2) = self.vertices();
+
+        // uniform barycentric coordinates
+        let su = rv.x.sqrt();
+        let b1 = 1.0 - su;
+        let b2 = rv.y * su;
+        let p = (1.0 - b1 - b2) * p0 + b1 * p1 + b2 * p2;
+        let gn = (p1 - p0).cross(p2 - p0).normalize();
+        let sn = self.shading_normal(1.0 - b1 - b2, b1, b2, gn);
+        // orient the geometric normal like intersect so both agree on the front face
+        let n = if gn.dot(sn) < 0.0 { -gn } else { gn };
+
+        rec.hit.uv = self.uv(1.0 - b1 - b2, b1, b2);
+        rec.hit.vertex_color = self.color(1.0 - b1 - b2, b1, b2);
+        let value = emitter_sample(rec, p, n, 1.0 / self.area(), &self.m_mesh.m_material);
+        rec.hit.sn = sn;
+        value
+    }
+}
+
+impl Triangle {
+    fn vertices(&self) -> (DVec3, DVec3, DVec3) {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        (mesh.positions[tri[0] as usize], mesh.positions[tri[1] as usize], mesh.positions[tri[2] as usize])
+    }
+
+    fn area(&self) -> f64 {
+        let (p0, p1, p2) = self.vertices();
+        0.5 * (p1 - p0).cross(p2 - p0).length()
+    }
+
+    // interpolated vertex normal at barycentrics (b0, b1, b2),
+    // the geometric normal gn if the mesh has none
+    fn shading_normal(&self, b0: f64, b1: f64, b2: f64, gn: DVec3) -> DVec3 {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        if mesh.normals.is_empty() {
+            return gn;
+        }
+        let n = b0 * mesh.normals[tri[0] as usize] + b1 * mesh.normals[tri[1] as usize] + b2 * mesh.normals[tri[2] as usize];
+        if n.length_squared() > 0.0 { n.normalize() } else { gn }
+    }
+
+    // interpolated texture coordinates at barycentrics (b0, b1, b2),
+    // the barycentrics themselves if the mesh has none
+    fn uv(&self, b0: f64, b1: f64, b2: f64) -> DVec2 {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        if !mesh.uvs.is_empty() {
+            b0 * mesh.uvs[tri[0] as usize] + b1 * mesh.uvs[tri[1] as usize] + b2 * mesh.uvs[tri[2] as usize]
+        } else {
+            DVec2 { x: b1 + b2, y: b2 }
+        }
+    }
+
+    // interpolated vertex color at barycentrics (b0, b1, b2), white if the mesh has none
+    fn color(&self, b0: f64, b1: f64, b2: f64) -> Color {
+        let mesh = self.m_mesh.as_ref();
+        let tri = mesh.triangles[self.m_index];
+        if mesh.colors.is_empty() {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+        let c = b0 * mesh.colors[tri[0] as usize] + b1 * mesh.colors[tri[1] as usize] + b2 * mesh.colors[tri[2] as usize];
+        Color::new(c.x, c.y, c.z)
+    }
+}
@@ -1,27 +1,101 @@
 mod sphere;
 mod quad;
+mod bvh;
+mod mesh;
+mod obj;
+mod ply;
 
 use serde_json::{Value};
 use sphere::Sphere;
 use quad::Quad;
+use bvh::{Bvh, BvhSettings, BvhStats};
+use mesh::{Mesh, Triangle};
 use crate::util::ray::{Ray, HitInfo};
+use crate::util::bbox::BBox;
+use crate::util::color::Color;
+use crate::material::{ObjectMaterial, Material};
+use glam::{DVec2, DVec3};
+use std::sync::Arc;
 
 // enum for all surfaces
+// large surfaces are behind a pointer to keep the enum small for meshes with
+// many triangles, and shared so the light list can reference them
 enum Surface {
-    SURFACEGROUP(SurfaceGroup),
-    SPHERE(Sphere),
-    QUAD(Quad)
+    #[allow(dead_code)]
+    SURFACEGROUP(Box<SurfaceGroup>),
+    SPHERE(Arc<Sphere>),
+    QUAD(Arc<Quad>),
+    TRIANGLE(Triangle),
+    BVH(Box<Bvh>)
+}
+
+// sampled point on an emitter as seen from a reference point
+pub struct EmitterRecord {
+    pub o: DVec3,       // reference point
+    pub wi: DVec3,      // unit direction from o to the sampled point
+    pub pdf: f64,       // solid angle density of wi
+    pub hit: HitInfo    // surface information at the sampled point
+}
+
+impl EmitterRecord {
+    pub fn new(o: DVec3) -> EmitterRecord {
+        EmitterRecord { o, wi: DVec3::ZERO, pdf: 0.0, hit: HitInfo::new() }
+    }
 }
 
 // base trait for all surfaces
 pub trait SurfaceBase {
     fn is_emissive(&self) -> bool { false }
     fn intersect(&self, ray: &mut Ray, hit: &mut HitInfo) -> bool;
+
+    // world space bounding box
+    fn bounds(&self) -> BBox;
+
+    // sample a point on the surface as seen from rec.o using random numbers rv
+    // fills in rec and returns emitted radiance divided by the pdf
+    fn sample(&self, _rec: &mut EmitterRecord, _rv: DVec2) -> Color { Color::BLACK }
+}
+
+// finish a sample at world point p with unit normal n, given its area density
+pub fn emitter_sample(rec: &mut EmitterRecord, p: DVec3, n: DVec3, pdf_area: f64, mat: &Arc<ObjectMaterial>) -> Color {
+    let to_light = p - rec.o;
+    let dist2 = to_light.length_squared();
+    let dist = dist2.sqrt();
+    let wi = to_light / dist;
+    let cos_theta = wi.dot(n).abs();
+    if cos_theta == 0.0 || dist == 0.0 {
+        rec.pdf = 0.0;
+        return Color::BLACK;
+    }
+
+    rec.wi = wi;
+    rec.pdf = pdf_area * dist2 / cos_theta;
+    rec.hit.t = dist;
+    rec.hit.p = p;
+    rec.hit.sn = n;
+    rec.hit.gn = n;
+    rec.hit.front_face = wi.dot(n) < 0.0;
+    rec.hit.mat = Arc::clone(mat);
+
+    let ray = Ray::new(rec.o, wi, None, None);
+    mat.emitted(&ray, &rec.hit) / rec.pdf
+}
+
+// convert an area density at a hit point to a solid angle density from the ray origin
+pub fn area_to_solid_angle(pdf_area: f64, ray: &Ray, hit: &HitInfo) -> f64 {
+    let dist2 = hit.t * hit.t * ray.d.length_squared();
+    let cos_theta = ray.d.normalize().dot(hit.gn).abs();
+    if cos_theta == 0.0 {
+        return 0.0;
+    }
+    pdf_area * dist2 / cos_theta
 }
 
 // collection of surfaces grouped together
 pub struct SurfaceGroup {
-    m_surfaces: Vec<Surface>
+    m_surfaces: Vec<Surface>,
+    m_lights: Vec<Surface>,     // emissive surfaces, shared with m_surfaces
+    pub m_bvh_stats: Option<BvhStats>   // build statistics if a bvh was built
 }
 
 impl SurfaceBase for SurfaceGroup {
@@ -37,6 +111,30 @@ impl SurfaceBase for SurfaceGroup {
 
         return hit_something;
     }
+
+    fn bounds(&self) -> BBox {
+        let mut bbox = BBox::empty();
+        for surface in self.m_surfaces.iter() {
+            bbox.enclose(&surface.bounds());
+        }
+        bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        !self.m_lights.is_empty()
+    }
+}
+
+impl Surface {
+    // share a leaf surface for use in the light list
+    fn share(&self) -> Option<Surface> {
+        match self {
+            Surface::SPHERE(s) => Some(Surface::SPHERE(Arc::clone(s))),
+            Surface::QUAD(s) => Some(Surface::QUAD(Arc::clone(s))),
+            Surface::TRIANGLE(s) => Some(Surface::TRIANGLE(s.clone())),
+            _ => None
+        }
+    }
 }
 
 impl SurfaceBase for Surface {
@@ -44,35 +142,132 @@ impl SurfaceBase for Surface {
         match self {
             Surface::SURFACEGROUP(s) => s.intersect(ray, hit),
             Surface::SPHERE(s) => s.intersect(ray, hit),
-            Surface::QUAD(s) => s.intersect(ray, hit)
+            Surface::QUAD(s) => s.intersect(ray, hit),
+            Surface::TRIANGLE(s) => s.intersect(ray, hit),
+            Surface::BVH(s) => s.intersect(ray, hit)
+        }
+    }
+
+    fn bounds(&self) -> BBox {
+        match self {
+            Surface::SURFACEGROUP(s) => s.bounds(),
+            Surface::SPHERE(s) => s.bounds(),
+            Surface::QUAD(s) => s.bounds(),
+            Surface::TRIANGLE(s) => s.bounds(),
+            Surface::BVH(s) => s.bounds()
+        }
+    }
+
+    fn is_emissive(&self) -> bool {
+        match self {
+            Surface::SURFACEGROUP(s) => s.is_emissive(),
+            Surface::SPHERE(s) => s.is_emissive(),
+            Surface::QUAD(s) => s.is_emissive(),
+            Surface::TRIANGLE(s) => s.is_emissive(),
+            Surface::BVH(_) => false
+        }
+    }
+
+    fn sample(&self, rec: &mut EmitterRecord, rv: DVec2) -> Color {
+        match self {
+            Surface::SPHERE(s) => s.sample(rec, rv),
+            Surface::QUAD(s) => s.sample(rec, rv),
+            Surface::TRIANGLE(s) => s.sample(rec, rv),
+            _ => Color::BLACK
         }
     }
 }
 
 impl SurfaceGroup {
     // create surface group from json
-    pub fn from_json(j: &Value) -> SurfaceGroup {
+    pub fn from_json(j: &Value) -> Result<SurfaceGroup, String> {
         let surface_array = match j.as_array() {
             Some(v) => v,
-            None => return SurfaceGroup { m_surfaces: Vec::new() }
+            None => return Ok(SurfaceGroup { m_surfaces: Vec::new(), m_lights: Vec::new(), m_bvh_stats: None })
         };
         let mut surface_agg:Vec<Surface> = Vec::new();
 
-        for v in surface_array.iter() {
-            let surface_object = v.as_object().unwrap();
-            let surface_type = surface_object.get("type").unwrap();
+        // surfaces are identified by their position in the scene file
+        for (id, v) in surface_array.iter().enumerate() {
+            let context = |e: String| format!("surface {}: {}", id, e);
+            let id = id as u32;
+            let surface_type = match v["type"].as_str() {
+                Some(t) => t,
+                None => return Err(context(String::from("surface without type")))
+            };
 
             if surface_type == "sphere" {
-                surface_agg.push(Surface::SPHERE(Sphere::from_json(v)));
+                surface_agg.push(Surface::SPHERE(Arc::new(Sphere::from_json(v, id).map_err(context)?)));
             };
             if surface_type == "quad" {
-                surface_agg.push(Surface::QUAD(Quad::from_json(v)));
+                surface_agg.push(Surface::QUAD(Arc::new(Quad::from_json(v, id).map_err(context)?)));
+            };
+            if surface_type == "mesh" {
+                surface_agg.extend(Mesh::from_json(v, id).map_err(context)?.into_iter().map(Surface::TRIANGLE));
             };
         }
 
-        SurfaceGroup {
-            m_surfaces: surface_agg
+        // gather emitters before the accelerator takes ownership of the surfaces
+        let lights = surface_agg.iter()
+            .filter(|s| s.is_emissive())
+            .filter_map(|s| s.share())
+            .collect();
+
+        Ok(SurfaceGroup {
+            m_surfaces: surface_agg,
+            m_lights: lights,
+            m_bvh_stats: None
+        })
+    }
+
+    pub fn light_count(&self) -> usize {
+        self.m_lights.len()
+    }
+
+    // solid angle density of sample_light() producing the emitter point
+    // the ray hit, from the area density the surface stored in hit
+    pub fn light_pdf(&self, ray: &Ray, hit: &HitInfo) -> f64 {
+        let n = self.m_lights.len();
+        if n == 0 || !hit.mat.is_emissive() {
+            return 0.0;
         }
+        area_to_solid_angle(hit.pdf_area, ray, hit) / n as f64
     }
 
-}
\ No newline at end of file
+    // pick one emitter uniformly using rv.x and sample a point on it
+    // the returned radiance and rec.pdf account for the selection probability
+    pub fn sample_light(&self, rec: &mut EmitterRecord, rv: DVec2) -> Color {
+        let n = self.m_lights.len();
+        if n == 0 {
+            rec.pdf = 0.0;
+            return Color::BLACK;
+        }
+
+        // reuse the part of rv.x left over from the selection
+        let scaled = rv.x * n as f64;
+        let index = (scaled as usize).min(n - 1);
+        let remapped = DVec2 { x: (scaled - index as f64).min(1.0 - f64::EPSILON), y: rv.y };
+
+        let radiance = self.m_lights[index].sample(rec, remapped);
+        rec.pdf /= n as f64;
+        radiance * n as f64
+    }
+
+    // build acceleration structure from the "accelerator" json block
+    // surfaces are left as a linear list if no known accelerator is requested
+    pub fn build_accelerator(&mut self, j: &Value) -> Result<(), String> {
+        let accel_type = match j.get("type") {
+            Some(v) => v.as_str().unwrap_or(""),
+            None => ""
+        };
+
+        if accel_type == "bbh" || accel_type == "bvh" {
+            let settings = BvhSettings::from_json(j)?;
+            let surfaces = std::mem::take(&mut self.m_surfaces);
+            let bvh = Bvh::new(surfaces, &settings);
+            self.m_bvh_stats = Some(bvh.m_stats.clone());
+            self.m_surfaces.push(Surface::BVH(Box::new(bvh)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use crate::surfaces::mesh::Mesh;
+use glam::{DVec2, DVec3};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+// parse a 1-based, possibly negative obj index into a 0-based index
+fn parse_index(s: &str, count: usize, line_no: usize) -> Result<Option<usize>, String> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let i: i64 = s.parse().map_err(|_| format!("line {}: invalid index \"{}\"", line_no, s))?;
+    let index = if i < 0 { count as i64 + i } else { i - 1 };
+    if index < 0 || index >= count as i64 {
+        return Err(format!("line {}: index {} out of range", line_no, i));
+    }
+    Ok(Some(index as usize))
+}
+
+fn parse_floats(parts: &[&str], n: usize, line_no: usize) -> Result<Vec<f64>, String> {
+    if parts.len() < n {
+        return Err(format!("line {}: expected {} values", line_no, n));
+    }
+    parts[..n].iter()
+        .map(|p| p.parse::<f64>().map_err(|_| format!("line {}: invalid number \"{}\"", line_no, p)))
+        .collect()
+}
+
+// load a wavefront obj file into a single mesh
+// if groups is given, only faces in the named groups ("g" or "o") are kept
+pub fn load_obj(filename: &str, groups: Option<&[String]>) -> Result<Mesh, String> {
+    let file = File::open(filename).map_err(|e| e.to_string())?;
+    parse_obj(BufReader::new(file), groups)
+}
+
+// polygons are triangulated as fans, vertices sharing the same position,
+// texture coordinate and normal indices are merged
+fn parse_obj<R: BufRead>(reader: R, groups: Option<&[String]>) -> Result<Mesh, String> {
+    let mut obj_positions: Vec<DVec3> = Vec::new();
+    let mut obj_normals: Vec<DVec3> = Vec::new();
+    let mut obj_uvs: Vec<DVec2> = Vec::new();
+    let mut obj_colors: Vec<DVec3> = Vec::new();
+
+    let mut positions: Vec<DVec3> = Vec::new();
+    let mut normals: Vec<DVec3> = Vec::new();
+    let mut uvs: Vec<DVec2> = Vec::new();
+    let mut colors: Vec<DVec3> = Vec::new();
+    let mut triangles: Vec<[u32; 3]> = Vec::new();
+    let mut has_normals = false;
+    let mut has_uvs = false;
+
+    // maps (position, uv, normal) obj indices to a mesh vertex
+    let mut vertex_map: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut group_active = true;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line_no = line_index + 1;
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => &line[..]
+        };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.is_empty() {
+            continue;
+        }
+
+        match parts[0] {
+            "v" => {
+                let v = parse_floats(&parts[1..], 3, line_no)?;
+                obj_positions.push(DVec3 { x: v[0], y: v[1], z: v[2] });
+
+                // non-standard "v x y z r g b" vertex colors
+                if parts.len() >= 7 {
+                    let c = parse_floats(&parts[4..], 3, line_no)?;
+                    obj_colors.resize(obj_positions.len() - 1, DVec3::ONE);
+                    obj_colors.push(DVec3 { x: c[0], y: c[1], z: c[2] });
+                }
+            },
+            "vn" => {
+                let v = parse_floats(&parts[1..], 3, line_no)?;
+                obj_normals.push(DVec3 { x: v[0], y: v[1], z: v[2] });
+            },
+            "vt" => {
+                let v = parse_floats(&parts[1..], 2, line_no)?;
+                obj_uvs.push(DVec2 { x: v[0], y: v[1] });
+            },
+            "g" | "o" => {
+                group_active = match groups {
+                    Some(names) => parts[1..].iter().any(|p| names.iter().any(|n| n == p)),
+                    None => true
+                };
+            },
+            "f" => {
+                if !group_active {
+                    continue;
+                }
+                if parts.len() < 4 {
+                    return Err(format!("line {}: face with fewer than 3 vertices", line_no));
+                }
+
+                let mut face: Vec<u32> = Vec::with_capacity(parts.len() - 1);
+                for vert in parts[1..].iter() {
+                    let mut fields = vert.split('/');
+                    let p = match parse_index(fields.next().unwrap_or(""), obj_positions.len(), line_no)? {
+                        Some(p) => p,
+                        None => return Err(format!("line {}: face vertex without position", line_no))
+                    };
+                    let t = parse_index(fields.next().unwrap_or(""), obj_uvs.len(), line_no)?;
+                    let n = parse_index(fields.next().unwrap_or(""), obj_normals.len(), line_no)?;
+
+                    let index = *vertex_map.entry((p, t, n)).or_insert_with(|| {
+                        positions.push(obj_positions[p]);
+                        normals.push(n.map_or(DVec3::ZERO, |n| obj_normals[n]));
+                        uvs.push(t.map_or(DVec2::ZERO, |t| obj_uvs[t]));
+                        colors.push(obj_colors.get(p).copied().unwrap_or(DVec3::ONE));
+                        (positions.len() - 1) as u32
+                    });
+                    has_normals |= n.is_some();
+                    has_uvs |= t.is_some();
+                    face.push(index);
+                }
+
+                for k in 1..face.len() - 1 {
+                    triangles.push([face[0], face[k], face[k + 1]]);
+                }
+            },
+            // materials, smoothing groups and other statements are ignored
+            _ => {}
+        }
+    }
+
+    if !has_normals {
+        normals.clear();
+    }
+    if !has_uvs {
+        uvs.clear();
+    }
+    if obj_colors.is_empty() {
+        colors.clear();
+    }
+
+    Ok(Mesh::new(positions, normals, uvs, colors, triangles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Mesh, String> {
+        parse_obj(text.as_bytes(), None)
+    }
+
+    fn error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => panic!("expected an error for {:?}", text),
+            Err(e) => e
+        }
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let mesh = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.normals.is_empty());
+        assert!(mesh.uvs.is_empty());
+        assert!(mesh.colors.is_empty());
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_end() {
+        let mesh = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n").unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+        assert_eq!(mesh.positions[2], DVec3 { x: 0.0, y: 1.0, z: 0.0 });
+    }
+
+    #[test]
+    fn vertices_are_merged_by_index_triple() {
+        let text = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 1
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1
+f 1/1/1 3/1/1 2/2/1
+";
+        let mesh = parse(text).unwrap();
+        // 2/2/1 differs from 2/1/1 in its uv and becomes a new vertex
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.uvs[3], DVec2 { x: 1.0, y: 1.0 });
+        assert_eq!(mesh.normals.len(), 4);
+    }
+
+    #[test]
+    fn position_and_normal_without_uv() {
+        let mesh = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n").unwrap();
+        assert_eq!(mesh.normals, vec![DVec3::Z; 3]);
+        assert!(mesh.uvs.is_empty());
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let mesh = parse("# header\nv 0 0 0 # first\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+        assert_eq!(mesh.triangles.len(), 1);
+    }
+
+    #[test]
+    fn vertex_colors() {
+        let mesh = parse("v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nf 1 2 3\n").unwrap();
+        assert_eq!(mesh.colors, vec![DVec3::X, DVec3::Y, DVec3::Z]);
+    }
+
+    #[test]
+    fn groups_filter_faces() {
+        let text = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+g first
+f 1 2 3
+o second
+f 2 4 3
+";
+        let groups = vec![String::from("second")];
+        let mesh = parse_obj(text.as_bytes(), Some(&groups)).unwrap();
+        assert_eq!(mesh.triangles.len(), 1);
+        assert_eq!(mesh.positions[mesh.triangles[0][1] as usize], DVec3 { x: 1.0, y: 1.0, z: 0.0 });
+
+        let mesh = parse_obj(text.as_bytes(), None).unwrap();
+        assert_eq!(mesh.triangles.len(), 2);
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"), "line 4: index 4 out of range");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n"), "line 4: index -4 out of range");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0\n"), "line 4: index 0 out of range");
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(error("v 0 0\n"), "line 1: expected 3 values");
+        assert_eq!(error("v 0 x 0\n"), "line 1: invalid number \"x\"");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nf 1 2\n"), "line 3: face with fewer than 3 vertices");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 a 3\n"), "line 4: invalid index \"a\"");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 /1 3\n"), "line 4: face vertex without position");
+    }
+}
@@ -0,0 +1,534 @@
+use crate::surfaces::mesh::Mesh;
+use crate::util::color::srgb_to_linear;
+use glam::{DVec2, DVec3};
+use std::io::BufRead;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    ASCII,
+    BINARYLE,
+    BINARYBE
+}
+
+// scalar types allowed in ply properties
+#[derive(Clone, Copy)]
+enum ScalarType {
+    I8, U8, I16, U16, I32, U32, F32, F64
+}
+
+impl ScalarType {
+    fn parse(s: &str) -> Result<ScalarType, String> {
+        match s {
+            "char" | "int8" => Ok(ScalarType::I8),
+            "uchar" | "uint8" => Ok(ScalarType::U8),
+            "short" | "int16" => Ok(ScalarType::I16),
+            "ushort" | "uint16" => Ok(ScalarType::U16),
+            "int" | "int32" => Ok(ScalarType::I32),
+            "uint" | "uint32" => Ok(ScalarType::U32),
+            "float" | "float32" => Ok(ScalarType::F32),
+            "double" | "float64" => Ok(ScalarType::F64),
+            _ => Err(format!("unknown property type \"{}\"", s))
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8
+        }
+    }
+
+    // integer colors are srgb encoded like srgb8 json colors, float colors are linear
+    fn color_to_linear(&self, value: f64) -> f64 {
+        match self {
+            ScalarType::U8 => srgb_to_linear(value / 255.0),
+            ScalarType::U16 => srgb_to_linear(value / 65535.0),
+            _ => value
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    value_type: ScalarType,
+    list_count_type: Option<ScalarType>   // set for list properties
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>
+}
+
+impl Element {
+    // fewest bytes a single record can take up, an ascii value is at least one
+    // character and an empty list is only its count
+    fn min_record_size(&self, format: Format) -> usize {
+        self.properties.iter().map(|p| match format {
+            Format::ASCII => 1,
+            _ => p.list_count_type.unwrap_or(p.value_type).size()
+        }).sum()
+    }
+}
+
+// reads values from the body of a ply file in any of the three encodings
+struct BodyReader<R: BufRead> {
+    reader: R,
+    format: Format,
+    tokens: Vec<String>,     // remaining tokens of the current ascii line
+    buf: [u8; 8]
+}
+
+impl<R: BufRead> BodyReader<R> {
+    fn next_token(&mut self) -> Result<String, String> {
+        while self.tokens.is_empty() {
+            let mut line = String::new();
+            let n = self.reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            if n == 0 {
+                return Err(String::from("unexpected end of file"));
+            }
+            self.tokens = line.split_whitespace().rev().map(String::from).collect();
+        }
+        Ok(self.tokens.pop().unwrap())
+    }
+
+    fn read(&mut self, t: ScalarType) -> Result<f64, String> {
+        if self.format == Format::ASCII {
+            let token = self.next_token()?;
+            return token.parse::<f64>().map_err(|_| format!("invalid value \"{}\"", token));
+        }
+
+        let size = t.size();
+        self.reader.read_exact(&mut self.buf[..size]).map_err(|e| e.to_string())?;
+        if self.format == Format::BINARYBE {
+            self.buf[..size].reverse();
+        }
+        let b = &self.buf;
+        let value = match t {
+            ScalarType::I8 => b[0] as i8 as f64,
+            ScalarType::U8 => b[0] as f64,
+            ScalarType::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            ScalarType::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            ScalarType::F64 => f64::from_le_bytes(*b)
+        };
+        Ok(value)
+    }
+}
+
+fn parse_header<R: BufRead>(reader: &mut R) -> Result<(Format, Vec<Element>), String> {
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    if line.trim_end() != "ply" {
+        return Err(String::from("missing ply magic number"));
+    }
+
+    let mut format: Option<Format> = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    loop {
+        line.clear();
+        let n = reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Err(String::from("unexpected end of header"));
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.is_empty() {
+            continue;
+        }
+
+        match parts[0] {
+            "format" => {
+                format = match parts.get(1) {
+                    Some(&"ascii") => Some(Format::ASCII),
+                    Some(&"binary_little_endian") => Some(Format::BINARYLE),
+                    Some(&"binary_big_endian") => Some(Format::BINARYBE),
+                    _ => return Err(format!("unknown format \"{}\"", line.trim_end()))
+                };
+            },
+            "element" => {
+                if parts.len() < 3 {
+                    return Err(format!("malformed element \"{}\"", line.trim_end()));
+                }
+                let count = parts[2].parse::<usize>().map_err(|_| format!("invalid element count \"{}\"", parts[2]))?;
+                elements.push(Element { name: String::from(parts[1]), count, properties: Vec::new() });
+            },
+            "property" => {
+                let element = match elements.last_mut() {
+                    Some(e) => e,
+                    None => return Err(String::from("property before any element"))
+                };
+                let property = if parts.get(1) == Some(&"list") {
+                    if parts.len() < 5 {
+                        return Err(format!("malformed property \"{}\"", line.trim_end()));
+                    }
+                    Property {
+                        name: String::from(parts[4]),
+                        value_type: ScalarType::parse(parts[3])?,
+                        list_count_type: Some(ScalarType::parse(parts[2])?)
+                    }
+                } else {
+                    if parts.len() < 3 {
+                        return Err(format!("malformed property \"{}\"", line.trim_end()));
+                    }
+                    Property {
+                        name: String::from(parts[2]),
+                        value_type: ScalarType::parse(parts[1])?,
+                        list_count_type: None
+                    }
+                };
+                element.properties.push(property);
+            },
+            "end_header" => break,
+            // comments and obj_info lines
+            _ => {}
+        }
+    }
+
+    match format {
+        Some(f) => Ok((f, elements)),
+        None => Err(String::from("missing format line"))
+    }
+}
+
+// load a stanford ply file in ascii or binary encoding
+pub fn load_ply(filename: &str) -> Result<Mesh, String> {
+    let data = std::fs::read(filename).map_err(|e| e.to_string())?;
+    parse_ply(&data)
+}
+
+// polygons are triangulated as fans
+fn parse_ply(data: &[u8]) -> Result<Mesh, String> {
+    let mut reader = data;
+    let (format, elements) = parse_header(&mut reader)?;
+
+    let mut body = BodyReader { reader, format, tokens: Vec::new(), buf: [0; 8] };
+
+    let mut positions: Vec<DVec3> = Vec::new();
+    let mut normals: Vec<DVec3> = Vec::new();
+    let mut uvs: Vec<DVec2> = Vec::new();
+    let mut colors: Vec<DVec3> = Vec::new();
+    let mut triangles: Vec<[u32; 3]> = Vec::new();
+
+    for element in elements.iter() {
+        // element counts come from the header, check them against the data
+        // left before allocating for them
+        if element.count > 0 && element.properties.is_empty() {
+            return Err(format!("element \"{}\" has no properties", element.name));
+        }
+        let available = body.reader.len() + body.tokens.len();
+        match element.count.checked_mul(element.min_record_size(format)) {
+            Some(n) if n <= available => {},
+            _ => return Err(format!("{} \"{}\" elements do not fit in the rest of the file", element.count, element.name))
+        }
+
+        if element.name == "vertex" {
+            // map property names to slots:
+            // 0-2 position, 3-5 normal, 6-7 uv, 8-10 color
+            let slots: Vec<Option<usize>> = element.properties.iter().map(|p| match p.name.as_str() {
+                "x" => Some(0), "y" => Some(1), "z" => Some(2),
+                "nx" => Some(3), "ny" => Some(4), "nz" => Some(5),
+                "u" | "s" | "texture_u" | "texture_s" => Some(6),
+                "v" | "t" | "texture_v" | "texture_t" => Some(7),
+                "red" | "r" => Some(8), "green" | "g" => Some(9), "blue" | "b" => Some(10),
+                _ => None
+            }).collect();
+            let has = |slot: usize| slots.contains(&Some(slot));
+            let has_normals = has(3) && has(4) && has(5);
+            let has_uvs = has(6) && has(7);
+            let has_colors = has(8) && has(9) && has(10);
+
+            positions.reserve(element.count);
+            let mut values = [0.0f64; 11];
+            for _ in 0..element.count {
+                for (prop, slot) in element.properties.iter().zip(slots.iter()) {
+                    if let Some(count_type) = prop.list_count_type {
+                        let n = body.read(count_type)? as usize;
+                        for _ in 0..n {
+                            body.read(prop.value_type)?;
+                        }
+                        continue;
+                    }
+                    let value = body.read(prop.value_type)?;
+                    if let Some(s) = slot {
+                        values[*s] = if *s >= 8 { prop.value_type.color_to_linear(value) } else { value };
+                    }
+                }
+                positions.push(DVec3 { x: values[0], y: values[1], z: values[2] });
+                if has_normals {
+                    normals.push(DVec3 { x: values[3], y: values[4], z: values[5] });
+                }
+                if has_uvs {
+                    uvs.push(DVec2 { x: values[6], y: values[7] });
+                }
+                if has_colors {
+                    colors.push(DVec3 { x: values[8], y: values[9], z: values[10] });
+                }
+            }
+        } else if element.name == "face" {
+            triangles.reserve(element.count);
+            let mut face: Vec<u32> = Vec::new();
+            for _ in 0..element.count {
+                for prop in element.properties.iter() {
+                    let count_type = match prop.list_count_type {
+                        Some(t) => t,
+                        None => {
+                            body.read(prop.value_type)?;
+                            continue;
+                        }
+                    };
+                    let n = body.read(count_type)? as usize;
+                    let is_indices = prop.name == "vertex_indices" || prop.name == "vertex_index";
+                    face.clear();
+                    for _ in 0..n {
+                        let v = body.read(prop.value_type)?;
+                        if is_indices {
+                            if !(v >= 0.0 && v <= u32::MAX as f64 && v.fract() == 0.0) {
+                                return Err(format!("invalid face index {}", v));
+                            }
+                            face.push(v as u32);
+                        }
+                    }
+                    if is_indices {
+                        for k in 1..face.len().saturating_sub(1) {
+                            triangles.push([face[0], face[k], face[k + 1]]);
+                        }
+                    }
+                }
+            }
+        } else {
+            // skip unknown elements
+            for _ in 0..element.count {
+                for prop in element.properties.iter() {
+                    let n = match prop.list_count_type {
+                        Some(t) => body.read(t)? as usize,
+                        None => 1
+                    };
+                    for _ in 0..n {
+                        body.read(prop.value_type)?;
+                    }
+                }
+            }
+        }
+    }
+
+    let vertex_count = positions.len() as u32;
+    if triangles.iter().any(|t| t.iter().any(|i| *i >= vertex_count)) {
+        return Err(String::from("face index out of range"));
+    }
+
+    Ok(Mesh::new(positions, normals, uvs, colors, triangles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &[u8]) -> Result<Mesh, String> {
+        parse_ply(data)
+    }
+
+    fn error(data: &[u8]) -> String {
+        match parse(data) {
+            Ok(_) => panic!("expected an error for {:?}", String::from_utf8_lossy(data)),
+            Err(e) => e
+        }
+    }
+
+    const TRIANGLE_HEADER: &str = "\
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    // single triangle in a binary encoding, values are written by the given function
+    fn binary_triangle(format: &str, to_bytes: fn(f32) -> [u8; 4], index_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut data = format!("ply\nformat {} 1.0\n{}", format, TRIANGLE_HEADER).into_bytes();
+        for v in [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            data.extend_from_slice(&to_bytes(v));
+        }
+        data.push(3);
+        for i in [0, 1, 2] {
+            data.extend_from_slice(&index_bytes(i));
+        }
+        data
+    }
+
+    #[test]
+    fn ascii_vertex_attributes() {
+        let data = b"\
+ply
+format ascii 1.0
+comment vertex attributes
+element vertex 3
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property float u
+property float v
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 0 0 255 0 0
+1 0 0 0 0 1 1 0 0 255 0
+0 1 0 0 0 1 0 1 0 0 255
+3 0 1 2
+";
+        let mesh = parse(data).unwrap();
+        assert_eq!(mesh.positions, vec![DVec3::ZERO, DVec3::X, DVec3::Y]);
+        assert_eq!(mesh.normals, vec![DVec3::Z; 3]);
+        assert_eq!(mesh.uvs[2], DVec2 { x: 0.0, y: 1.0 });
+        assert_eq!(mesh.colors, vec![DVec3::X, DVec3::Y, DVec3::Z]);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn integer_colors_are_srgb_decoded() {
+        let data = b"\
+ply
+format ascii 1.0
+element vertex 1
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+end_header
+0 0 0 128 0 255
+";
+        let mesh = parse(data).unwrap();
+        let c = mesh.colors[0];
+        assert!((c.x - srgb_to_linear(128.0 / 255.0)).abs() < 1e-12);
+        assert_eq!((c.y, c.z), (0.0, 1.0));
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        let mesh = parse(&binary_triangle("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes)).unwrap();
+        assert_eq!(mesh.positions, vec![DVec3::ZERO, DVec3::X, DVec3::Y]);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn binary_big_endian() {
+        let mesh = parse(&binary_triangle("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes)).unwrap();
+        assert_eq!(mesh.positions, vec![DVec3::ZERO, DVec3::X, DVec3::Y]);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let data = b"\
+ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+        let mesh = parse(data).unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.normals.is_empty());
+        assert!(mesh.uvs.is_empty());
+        assert!(mesh.colors.is_empty());
+    }
+
+    #[test]
+    fn unknown_properties_and_elements_are_skipped() {
+        let data = b"\
+ply
+format ascii 1.0
+element vertex 3
+property float x
+property list uchar float weights
+property float y
+property float z
+element material 1
+property list uchar uchar name
+element face 1
+property uchar flags
+property list uchar uint vertex_index
+property list uchar float texcoord
+end_header
+0 2 0.5 0.5 0 0
+1 0 0 0
+0 0 1 0
+3 7 7 7
+9 3 0 1 2 2 0.5 0.5
+";
+        let mesh = parse(data).unwrap();
+        assert_eq!(mesh.positions, vec![DVec3::ZERO, DVec3::X, DVec3::Y]);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn header_errors() {
+        assert_eq!(error(b"obj\n"), "missing ply magic number");
+        assert_eq!(error(b"ply\nelement vertex 0\nend_header\n"), "missing format line");
+        assert_eq!(error(b"ply\nformat ascii 1.0\nelement vertex 0\n"), "unexpected end of header");
+        assert_eq!(error(b"ply\nformat binary 1.0\nend_header\n"), "unknown format \"format binary 1.0\"");
+        assert_eq!(error(b"ply\nformat ascii 1.0\nproperty float x\nend_header\n"), "property before any element");
+        assert_eq!(error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n"), "unknown property type \"half\"");
+        assert_eq!(error(b"ply\nformat ascii 1.0\nelement vertex x\nend_header\n"), "invalid element count \"x\"");
+    }
+
+    #[test]
+    fn face_index_errors() {
+        let face = |f: &str| format!("ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n0 1 0\n{}\n", TRIANGLE_HEADER, f);
+        assert_eq!(error(face("3 0 1 3").as_bytes()), "face index out of range");
+        assert_eq!(error(face("3 0 -1 2").as_bytes()), "invalid face index -1");
+        assert_eq!(error(face("3 0 1.5 2").as_bytes()), "invalid face index 1.5");
+        assert_eq!(error(face("3 0 nan 2").as_bytes()), "invalid face index NaN");
+    }
+
+    #[test]
+    fn truncated_body() {
+        let ascii = format!("ply\nformat ascii 1.0\n{}0 0 0\n1 0 0\n", TRIANGLE_HEADER);
+        assert_eq!(error(ascii.as_bytes()), "unexpected end of file");
+        assert_eq!(error(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\nzero\n"), "invalid value \"zero\"");
+
+        let mut binary = binary_triangle("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        binary.truncate(binary.len() - 2);
+        assert!(parse(&binary).is_err());
+    }
+
+    #[test]
+    fn counts_larger_than_the_file() {
+        let header = "ply\nformat binary_little_endian 1.0\nelement vertex 100000000000000\nproperty float x\nproperty float y\nproperty float z\nend_header\n";
+        assert_eq!(error(header.as_bytes()), "100000000000000 \"vertex\" elements do not fit in the rest of the file");
+
+        let header = "ply\nformat ascii 1.0\nelement face 18446744073709551615\nproperty list uchar int vertex_indices\nend_header\n3 0 1 2\n";
+        assert_eq!(error(header.as_bytes()), "18446744073709551615 \"face\" elements do not fit in the rest of the file");
+
+        // the triangle header promises 3 vertices of 12 bytes each
+        let mut binary = binary_triangle("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes);
+        let header_len = binary.len() - 9 * 4 - 1 - 3 * 4;
+        binary.truncate(header_len + 20);
+        assert_eq!(error(&binary), "3 \"vertex\" elements do not fit in the rest of the file");
+
+        assert_eq!(error(b"ply\nformat ascii 1.0\nelement vertex 5\nend_header\n"), "element \"vertex\" has no properties");
+    }
+}
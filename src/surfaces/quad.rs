@@ -1,15 +1,22 @@
 use crate::util::*;
 use serde_json::{Value};
 use crate::util::transform::Transform;
-use crate::surfaces::SurfaceBase;
-use glam::DVec3;
+use crate::surfaces::{SurfaceBase, EmitterRecord, emitter_sample};
+use crate::util::color::Color;
+use glam::{DVec2, DVec3};
 use crate::material::{ObjectMaterial, Material};
-use std::rc::Rc;
+use crate::util::bbox::BBox;
+use std::sync::Arc;
+
+// normal of the quad in local space
+const QUAD_NORMAL: DVec3 = DVec3 { x: 0.0, y: 0.0, z: -1.0 };
 
 pub struct Quad {
     m_size: f64,
     m_xform: Transform,  // local to world
-    m_material: Rc<ObjectMaterial>
+    m_material: Arc<ObjectMaterial>,
+    m_id: u32,
+    m_bbox: BBox         // world space bounds
 }
 
 impl SurfaceBase for Quad {
@@ -34,21 +41,62 @@ impl SurfaceBase for Quad {
         p.z = 0.0;
 
         // get normal
-        let norm = self.m_xform.normal(DVec3 { x: 0.0, y: 0.0, z: -1.0 });
+        let norm = self.m_xform.normal(QUAD_NORMAL);
 
         // return true, record values
         hit.t = t;
         hit.p = self.m_xform.point(p);
         hit.sn = norm;
-        hit.mat = Rc::clone(&self.m_material);
+        hit.gn = hit.sn;
+        hit.front_face = ray.d.dot(hit.gn) < 0.0;
+        hit.uv = self.uv(p);
+        hit.vertex_color = Color::new(1.0, 1.0, 1.0);
+        hit.pdf_area = self.area_pdf();
+        hit.mat = Arc::clone(&self.m_material);
+        hit.object_id = self.m_id;
 
         return true;
     }
+
+    fn bounds(&self) -> BBox {
+        self.m_bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.m_material.is_emissive()
+    }
+
+    // sample uniformly by area
+    fn sample(&self, rec: &mut EmitterRecord, rv: DVec2) -> Color {
+        let p_local = DVec3 {
+            x: (2.0 * rv.x - 1.0) * self.m_size,
+            y: (2.0 * rv.y - 1.0) * self.m_size,
+            z: 0.0
+        };
+        let p = self.m_xform.point(p_local);
+        let n = self.m_xform.normal(QUAD_NORMAL);
+
+        rec.hit.uv = self.uv(p_local);
+        emitter_sample(rec, p, n, self.area_pdf(), &self.m_material)
+    }
 }
 
 impl Quad {
-    pub fn from_json(j: &Value) -> Quad {
-        let j = j.as_object().unwrap();
+    // planar texture coordinates spanning [0, 1] over the quad
+    fn uv(&self, p_local: DVec3) -> DVec2 {
+        DVec2 {
+            x: 0.5 * (p_local.x / self.m_size + 1.0),
+            y: 0.5 * (p_local.y / self.m_size + 1.0)
+        }
+    }
+
+    // world space area density of uniform sampling
+    fn area_pdf(&self) -> f64 {
+        let local_area = 4.0 * self.m_size * self.m_size;
+        1.0 / (local_area * self.m_xform.area_scale(QUAD_NORMAL))
+    }
+
+    pub fn from_json(j: &Value, id: u32) -> Result<Quad, String> {
 
         let size = match j.get("size") {
             Some(v) => safe_value_to_f64(v, 1.0),
@@ -56,15 +104,21 @@ impl Quad {
         };
 
         let transform = match j.get("transform") {
-            Some(v) => Transform::from_json(v),
+            Some(v) => Transform::from_json(v)?,
             None => Transform::identity()
         };
 
         let material = match j.get("material") {
-            Some(v) => ObjectMaterial::from_json(v),
-            None => panic!("can't parse without material")
+            Some(v) => ObjectMaterial::from_json(v)?,
+            None => return Err(String::from("can't parse without material"))
         };
 
-        Quad { m_size: size / 2.0, m_xform: transform, m_material: Rc::new(material) }
+        // pad so the flat quad has non-zero volume
+        let half = size / 2.0;
+        let bbox = BBox::new(DVec3 { x: -half, y: -half, z: 0.0 }, DVec3 { x: half, y: half, z: 0.0 })
+            .transformed(&transform)
+            .pad(EPSILON);
+
+        Ok(Quad { m_size: half, m_xform: transform, m_material: Arc::new(material), m_id: id, m_bbox: bbox })
     }
 }
\ No newline at end of file
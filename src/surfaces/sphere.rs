@@ -1,14 +1,19 @@
 use crate::util::*;
 use serde_json::{Value};
 use crate::util::transform::Transform;
-use crate::surfaces::SurfaceBase;
+use crate::surfaces::{SurfaceBase, EmitterRecord, emitter_sample};
+use crate::util::color::Color;
 use crate::material::{ObjectMaterial, Material};
-use std::rc::Rc;
+use crate::util::bbox::BBox;
+use glam::{DVec2, DVec3};
+use std::sync::Arc;
 
 pub struct Sphere {
     m_radius: f64,
     m_xform: Transform,  // local to world,
-    m_material: Rc<ObjectMaterial>
+    m_material: Arc<ObjectMaterial>,
+    m_id: u32,
+    m_bbox: BBox         // world space bounds
 }
 
 impl SurfaceBase for Sphere {
@@ -48,16 +53,53 @@ impl SurfaceBase for Sphere {
         hit.t = t;
         hit.p = self.m_xform.point(p);
         hit.sn = self.m_xform.normal(p);
-        hit.mat = Rc::clone(&self.m_material);
+        hit.gn = hit.sn;
+        hit.front_face = ray.d.dot(hit.gn) < 0.0;
+        hit.uv = sphere_uv(p / self.m_radius);
+        hit.vertex_color = Color::new(1.0, 1.0, 1.0);
+        hit.pdf_area = self.area_pdf(p.normalize());
+        hit.mat = Arc::clone(&self.m_material);
+        hit.object_id = self.m_id;
 
         return true;
 
     }
+
+    fn bounds(&self) -> BBox {
+        self.m_bbox
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.m_material.is_emissive()
+    }
+
+    // sample uniformly by area
+    fn sample(&self, rec: &mut EmitterRecord, rv: DVec2) -> Color {
+        let n_local = random_on_unit_sphere(rv.x, rv.y);
+        let p = self.m_xform.point(n_local * self.m_radius);
+        let n = self.m_xform.normal(n_local);
+
+        rec.hit.uv = sphere_uv(n_local);
+        emitter_sample(rec, p, n, self.area_pdf(n_local), &self.m_material)
+    }
+}
+
+// spherical texture coordinates of a point on the unit sphere, u goes around
+// the local y axis starting at -x and v runs from the bottom pole to the top
+fn sphere_uv(n: DVec3) -> DVec2 {
+    let theta = (-n.y).clamp(-1.0, 1.0).acos();
+    let phi = (-n.z).atan2(n.x) + M_PI;
+    DVec2 { x: phi / (2.0 * M_PI), y: theta / M_PI }
 }
 
 impl Sphere {
-    pub fn from_json(j: &Value) -> Sphere {
-        let j = j.as_object().unwrap();
+    // world space area density of uniform sampling, at local normal n_local
+    fn area_pdf(&self, n_local: DVec3) -> f64 {
+        let local_area = 4.0 * M_PI * self.m_radius * self.m_radius;
+        1.0 / (local_area * self.m_xform.area_scale(n_local))
+    }
+
+    pub fn from_json(j: &Value, id: u32) -> Result<Sphere, String> {
 
         let radius = match j.get("radius") {
             Some(v) => safe_value_to_f64(v, 1.0),
@@ -65,15 +107,17 @@ impl Sphere {
         };
 
         let transform = match j.get("transform") {
-            Some(v) => Transform::from_json(v),
+            Some(v) => Transform::from_json(v)?,
             None => Transform::identity()
         };
 
         let material = match j.get("material") {
-            Some(v) => ObjectMaterial::from_json(v),
-            None => panic!("can't parse without material")
+            Some(v) => ObjectMaterial::from_json(v)?,
+            None => return Err(String::from("can't parse without material"))
         };
 
-        Sphere { m_radius: radius, m_xform: transform, m_material: Rc::new(material) }
+        let bbox = BBox::new(DVec3::splat(-radius), DVec3::splat(radius)).transformed(&transform);
+
+        Ok(Sphere { m_radius: radius, m_xform: transform, m_material: Arc::new(material), m_id: id, m_bbox: bbox })
     }
 }
\ No newline at end of file
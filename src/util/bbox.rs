@@ -0,0 +1,90 @@
+use glam::DVec3;
+use crate::util::max_dimension;
+use crate::util::ray::Ray;
+use crate::util::transform::Transform;
+
+// axis aligned bounding box
+#[derive(Clone, Copy)]
+pub struct BBox {
+    pub min: DVec3,
+    pub max: DVec3
+}
+
+impl BBox {
+    // empty box that any point will expand
+    pub fn empty() -> BBox {
+        BBox { min: DVec3::splat(f64::INFINITY), max: DVec3::splat(f64::NEG_INFINITY) }
+    }
+
+    pub fn new(a: DVec3, b: DVec3) -> BBox {
+        BBox { min: a.min(b), max: a.max(b) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    // grow box to contain point
+    pub fn enclose_point(&mut self, p: DVec3) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    // grow box to contain other box
+    pub fn enclose(&mut self, b: &BBox) {
+        self.min = self.min.min(b.min);
+        self.max = self.max.max(b.max);
+    }
+
+    pub fn center(&self) -> DVec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn diagonal(&self) -> DVec3 {
+        if self.is_empty() {
+            return DVec3::ZERO;
+        }
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // index of longest axis
+    pub fn max_extent(&self) -> usize {
+        max_dimension(self.diagonal())
+    }
+
+    // grow box by a small amount on every side, for flat surfaces
+    pub fn pad(&self, amount: f64) -> BBox {
+        BBox { min: self.min - DVec3::splat(amount), max: self.max + DVec3::splat(amount) }
+    }
+
+    // world space box of a transformed local space box
+    pub fn transformed(&self, xform: &Transform) -> BBox {
+        let mut result = BBox::empty();
+        for i in 0..8 {
+            let corner = DVec3 {
+                x: if i & 1 == 0 { self.min.x } else { self.max.x },
+                y: if i & 2 == 0 { self.min.y } else { self.max.y },
+                z: if i & 4 == 0 { self.min.z } else { self.max.z }
+            };
+            result.enclose_point(xform.point(corner));
+        }
+        result
+    }
+
+    // slab test, inv_d is the precomputed reciprocal of the ray direction
+    pub fn intersect(&self, ray: &Ray, inv_d: DVec3) -> bool {
+        let t0 = (self.min - ray.o) * inv_d;
+        let t1 = (self.max - ray.o) * inv_d;
+
+        let t_near = t0.min(t1).max_element().max(ray.mint);
+        let t_far = t0.max(t1).min_element().min(ray.maxt);
+
+        // small margin so rays grazing a box edge are not lost
+        t_near <= t_far * (1.0 + 2.0 * f64::EPSILON)
+    }
+}
@@ -0,0 +1,116 @@
+use std::ops::{Add, AddAssign, Mul, MulAssign, Div};
+use serde_json::{Value};
+
+// linear rgb radiance, unbounded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64
+}
+
+impl Color {
+    pub const BLACK: Color = Color { r: 0.0, g: 0.0, b: 0.0 };
+
+    pub fn new(r: f64, g: f64, b: f64) -> Color {
+        Color { r, g, b }
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    // parse color from a json array of linear values, or from an object
+    // {"srgb8": [r, g, b]} of srgb encoded 8 bit values in [0, 255] like
+    // the texels of 8 bit image textures
+    pub fn from_json(v: &Value, default: Color) -> Result<Color, String> {
+        let (x, srgb8) = match v.get("srgb8") {
+            Some(x) => (x, true),
+            None => (v, false)
+        };
+        let x = match x.as_array() {
+            Some(x) if x.len() >= 3 => &x[..3],
+            _ if srgb8 => return Err(format!("srgb8 color must be an array of 3 numbers, got {}", x)),
+            _ => return Ok(default)
+        };
+        if x.iter().any(|c| !c.is_number()) {
+            return Err(format!("color components must be numbers, got {}", v));
+        }
+
+        let component = |c: &Value| {
+            let c = c.as_f64().unwrap();
+            if srgb8 { srgb_to_linear(c / 255.0) } else { c }
+        };
+        Ok(Color { r: component(&x[0]), g: component(&x[1]), b: component(&x[2]) })
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+    fn add(self, o: Color) -> Color {
+        Color { r: self.r + o.r, g: self.g + o.g, b: self.b + o.b }
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, o: Color) {
+        self.r += o.r;
+        self.g += o.g;
+        self.b += o.b;
+    }
+}
+
+// component-wise attenuation
+impl Mul for Color {
+    type Output = Color;
+    fn mul(self, o: Color) -> Color {
+        Color { r: self.r * o.r, g: self.g * o.g, b: self.b * o.b }
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, o: Color) {
+        self.r *= o.r;
+        self.g *= o.g;
+        self.b *= o.b;
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Color;
+    fn mul(self, s: f64) -> Color {
+        Color { r: self.r * s, g: self.g * s, b: self.b * s }
+    }
+}
+
+impl Mul<Color> for f64 {
+    type Output = Color;
+    fn mul(self, c: Color) -> Color {
+        c * self
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Color;
+    fn div(self, s: f64) -> Color {
+        Color { r: self.r / s, g: self.g / s, b: self.b / s }
+    }
+}
+
+// srgb transfer function (IEC 61966-2-1)
+pub fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// inverse of the srgb transfer function
+pub fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -1,16 +1,89 @@
 pub mod transform;
 pub mod ray;
+pub mod bbox;
+pub mod color;
 
 use serde_json::{Value};
-use glam::DVec3;
+use glam::{DVec2, DVec3};
 
-pub const M_PI: f64 = 3.14159265358979323846;
+pub const M_PI: f64 = std::f64::consts::PI;
 pub const EPSILON: f64 = 0.001;
 pub const MAX_RAYTRACE_DEPTH: i32 = 64;
 
+// splitmix64 finalizer, scrambles bits of a 64 bit value
+pub fn hash_u64(v: u64) -> u64 {
+    let mut z = v.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// seed of the random stream used for a single pixel
+pub fn pixel_seed(seed: u64, x: u32, y: u32) -> u64 {
+    hash_u64(seed ^ hash_u64(((y as u64) << 32) | x as u64))
+}
+
 // convert degrees to radians
 pub fn deg_2_rad(v: f64) -> f64 { v * (M_PI / 180.0) }
 
+// index of largest component
+pub fn max_dimension(v: DVec3) -> usize {
+    if v.x > v.y && v.x > v.z {
+        0
+    } else if v.y > v.z {
+        1
+    } else {
+        2
+    }
+}
+
+// mirror direction v about normal n
+pub fn reflect(v: DVec3, n: DVec3) -> DVec3 {
+    v - 2.0 * v.dot(n) * n
+}
+
+// refract unit direction v through unit normal n facing against v
+// eta is the ratio of indices of refraction (incident over transmitted)
+// returns None on total internal reflection
+pub fn refract(v: DVec3, n: DVec3, eta: f64) -> Option<DVec3> {
+    let cos_i = (-v.dot(n)).min(1.0);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t > 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(eta * v + (eta * cos_i - cos_t) * n)
+}
+
+// build two unit vectors perpendicular to unit vector n and each other
+pub fn coordinate_system(n: DVec3) -> (DVec3, DVec3) {
+    let sign = 1.0_f64.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        DVec3 { x: 1.0 + sign * n.x * n.x * a, y: sign * b, z: -sign * n.x },
+        DVec3 { x: b, y: sign + n.y * n.y * a, z: -n.y }
+    )
+}
+
+// direction distributed proportional to cos^exponent around unit axis
+pub fn sample_phong_lobe(axis: DVec3, exponent: f64, x: f64, y: f64) -> DVec3 {
+    let cos_theta = (1.0 - x).powf(1.0 / (exponent + 1.0));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * M_PI * y;
+    let (t, b) = coordinate_system(axis);
+    (t * phi.cos() + b * phi.sin()) * sin_theta + axis * cos_theta
+}
+
+// density of sample_phong_lobe producing unit direction v
+pub fn phong_lobe_pdf(axis: DVec3, exponent: f64, v: DVec3) -> f64 {
+    let cos_alpha = axis.dot(v);
+    if cos_alpha <= 0.0 {
+        return 0.0;
+    }
+    (exponent + 1.0) / (2.0 * M_PI) * cos_alpha.powf(exponent)
+}
+
 // get point on unit sphere from random sample
 pub fn random_on_unit_sphere(x: f64, y: f64) -> DVec3 {
     let phi = x * 2.0 * M_PI;
@@ -24,6 +97,41 @@ pub fn random_on_unit_sphere(x: f64, y: f64) -> DVec3 {
     }
 }
 
+// uniform point on the unit disk from random sample, concentric mapping
+pub fn sample_disk(x: f64, y: f64) -> DVec2 {
+    let a = 2.0 * x - 1.0;
+    let b = 2.0 * y - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return DVec2::ZERO;
+    }
+
+    let (r, phi) = if a.abs() > b.abs() {
+        (a, (M_PI / 4.0) * (b / a))
+    } else {
+        (b, (M_PI / 2.0) - (M_PI / 4.0) * (a / b))
+    };
+    DVec2 { x: r * phi.cos(), y: r * phi.sin() }
+}
+
+// uniform point on a regular polygon inscribed in the unit circle
+// with n corners, the first at angle rotation
+pub fn sample_polygon(n: u32, rotation: f64, x: f64, y: f64) -> DVec2 {
+    // pick one of the triangles fanning out from the center, reusing
+    // the part of x left over from the selection
+    let scaled = x * n as f64;
+    let k = (scaled as u32).min(n - 1);
+    let x = scaled - k as f64;
+
+    let corner = |i: u32| {
+        let phi = rotation + 2.0 * M_PI * i as f64 / n as f64;
+        DVec2 { x: phi.cos(), y: phi.sin() }
+    };
+
+    // uniform barycentrics in the triangle (center, corner k, corner k + 1)
+    let su = x.sqrt();
+    corner(k) * (su * (1.0 - y)) + corner(k + 1) * (su * y)
+}
+
 // convert value to f64 or default on error
 pub fn safe_value_to_f64(v: &Value, default: f64) -> f64 {
     match v.as_f64() {
@@ -39,34 +147,3 @@ pub fn safe_value_to_i64(v: &Value, default: i64) -> i64 {
         None => default
     }
 }
-
-// convert value to color
-pub fn safe_value_to_color(v: &Value, default: image::Rgb<u8>) -> image::Rgb<u8> {
-    match v.as_array() {
-        Some(x) => {
-            image::Rgb([
-                safe_value_to_i64(&x[0], 0).try_into().unwrap(),
-                safe_value_to_i64(&x[1], 0).try_into().unwrap(),
-                safe_value_to_i64(&x[2], 0).try_into().unwrap()
-            ])
-        },
-        None => default
-    }
-}
-
-// attenuate b by a
-pub fn compose_color(a: image::Rgb<u8>, b: image::Rgb<u8>) -> image::Rgb<u8> {
-    let r_att: f32 = (a[0] as f32 / 255.0) * b[0] as f32;
-    let g_att: f32 = (a[1] as f32 / 255.0) * b[1] as f32;
-    let b_att: f32 = (a[2] as f32 / 255.0) * b[2] as f32;
-
-    image::Rgb([
-        r_att.round() as u8,
-        g_att.round() as u8,
-        b_att.round() as u8
-    ])
-}
-
-pub fn add_color(a: image::Rgb<u8>, b: image::Rgb<u8>) -> image::Rgb<u8> {
-    image::Rgb([a[0]+b[0], a[1]+b[1], a[2]+b[2]])
-}
\ No newline at end of file
@@ -1,7 +1,8 @@
 use crate::util::*;
 use crate::material::{ObjectMaterial};
-use glam::f64::DVec3;
-use std::rc::Rc;
+use crate::util::color::Color;
+use glam::f64::{DVec2, DVec3};
+use std::sync::Arc;
 
 pub struct Ray {
     pub o: DVec3,    // origin of ray
@@ -17,14 +18,8 @@ impl Ray {
     }
 
     pub fn new(origin: DVec3, direction: DVec3, mint: Option<f64>, maxt: Option<f64>) -> Ray {
-        let t_min = match mint {
-            Some(x) => x,
-            None => EPSILON
-        };
-        let t_max = match maxt {
-            Some(x) => x,
-            None => f64::INFINITY
-        };
+        let t_min = mint.unwrap_or(EPSILON);
+        let t_max = maxt.unwrap_or(f64::INFINITY);
 
         Ray { o: origin, d: direction, mint: t_min, maxt: t_max }
     }
@@ -35,11 +30,28 @@ pub struct HitInfo {
     pub t: f64,                 // ray parameter for hit
     pub p: DVec3,               // hit position
     pub sn: DVec3,              // shading normal
-    pub mat: Rc<ObjectMaterial>     // material at hit point
+    pub gn: DVec3,              // geometric normal
+    pub uv: DVec2,              // surface texture coordinates
+    pub vertex_color: Color,    // interpolated mesh vertex color, white if there is none
+    pub front_face: bool,       // ray hit the side the normals point to
+    pub object_id: u32,         // index of the scene surface entry that was hit
+    pub pdf_area: f64,          // area density of sampling the hit point as an emitter
+    pub mat: Arc<ObjectMaterial>     // material at hit point
 }
 
 impl HitInfo {
     pub fn new() -> HitInfo {
-        HitInfo { t: 0.0, p: DVec3::ZERO, sn: DVec3::ZERO, mat: Rc::new(ObjectMaterial::EMPTY) }
+        HitInfo {
+            t: 0.0,
+            p: DVec3::ZERO,
+            sn: DVec3::ZERO,
+            gn: DVec3::ZERO,
+            uv: DVec2::ZERO,
+            vertex_color: Color::new(1.0, 1.0, 1.0),
+            front_face: true,
+            object_id: 0,
+            pdf_area: 0.0,
+            mat: Arc::new(ObjectMaterial::EMPTY)
+        }
     }
 }
\ No newline at end of file
@@ -25,6 +25,12 @@ impl Transform {
         self.m_inv.transpose().transform_vector3(n).normalize()
     }
 
+    // ratio of world to local area for a small patch with local normal n
+    pub fn area_scale(&self, n: DVec3) -> f64 {
+        let det = glam::DMat3::from_mat4(self.m).determinant().abs();
+        det * self.m_inv.transpose().transform_vector3(n).length()
+    }
+
     // transform ray
     pub fn ray(&self, r: &Ray) -> Ray {
         Ray::new(self.point(r.o), self.vector(r.d), Some(r.mint), Some(r.maxt))
@@ -40,44 +46,104 @@ impl Transform {
         Transform { m: DMat4::IDENTITY , m_inv: DMat4::IDENTITY }
     }
 
-    pub fn from_json(j: &Value) -> Transform {
-        let j = j.as_object().unwrap();
-        let translation_vec = match j.get("o") {
-            Some(v) => match v.as_array() {
-                Some(x) => DVec3 { 
-                    x: safe_value_to_f64(&x[0], 0.0), 
-                    y: safe_value_to_f64(&x[1], 0.0), 
-                    z: safe_value_to_f64(&x[2], 0.0)
-                },
-                None => DVec3::ZERO
-            },
-            None => DVec3::ZERO
-        };
-
-        let axis_vec = match j.get("axis") {
-            Some(v) => {
-                let val_arr = v.as_array().unwrap();
-                DVec3 {
-                    x: safe_value_to_f64(&val_arr[0], 0.0),
-                    y: safe_value_to_f64(&val_arr[1], 0.0),
-                    z: safe_value_to_f64(&val_arr[2], 0.0)
+    // parse either an ordered list of operations applied first to last
+    //   [ { "translate": [x, y, z] },
+    //     { "rotate": { "axis": [x, y, z], "angle": degrees } },
+    //     { "scale": s or [x, y, z] },
+    //     { "matrix": [16 values, row major] },
+    //     { "lookat": { "from": [x, y, z], "at": [x, y, z], "up": [x, y, z] } } ]
+    // a single operation object, or the original { "o", "axis", "angle" } form
+    // with the angle in units of pi, an empty array or object is the identity
+    pub fn from_json(j: &Value) -> Result<Transform, String> {
+        let m = match j {
+            Value::Object(o) if o.is_empty() => DMat4::IDENTITY,
+            Value::Array(ops) => {
+                let mut m = DMat4::IDENTITY;
+                for op in ops.iter() {
+                    m = operation_from_json(op)? * m;
                 }
+                m
             },
-            None => DVec3::ZERO
+            Value::Object(o) if o.contains_key("o") || o.contains_key("axis") || o.contains_key("angle") => legacy_from_json(j),
+            Value::Object(_) => operation_from_json(j)?,
+            _ => return Err(format!("can't parse transform {}", j))
         };
 
-        let angle = match j.get("angle") {
-            Some(v) => M_PI * safe_value_to_f64(v, 0.0),
-            None => 0.0
-        };
-
-        let transform_mat = DMat4::from_rotation_translation(
-            DQuat::from_axis_angle(axis_vec, angle), 
-            translation_vec);
+        Ok(Transform {
+            m,
+            m_inv: m.inverse()
+        })
+    }
+}
 
-        Transform {
-            m: transform_mat,
-            m_inv: transform_mat.inverse()
-        }
+// parse a json array of 3 numbers
+fn vec3_from_json(v: &Value, default: DVec3) -> DVec3 {
+    match v.as_array() {
+        Some(x) if x.len() >= 3 => DVec3 {
+            x: safe_value_to_f64(&x[0], default.x),
+            y: safe_value_to_f64(&x[1], default.y),
+            z: safe_value_to_f64(&x[2], default.z)
+        },
+        _ => default
     }
-}
\ No newline at end of file
+}
+
+// matrix of a single transform operation
+fn operation_from_json(j: &Value) -> Result<DMat4, String> {
+    let (name, v) = match j.as_object() {
+        Some(o) if o.len() == 1 => o.iter().next().unwrap(),
+        _ => return Err(format!("transform operation must be an object with a single key, got {}", j))
+    };
+
+    let m = match name.as_str() {
+        "translate" => DMat4::from_translation(vec3_from_json(v, DVec3::ZERO)),
+        "rotate" => {
+            let axis = vec3_from_json(&v["axis"], DVec3::Y).try_normalize()
+                .ok_or_else(|| format!("rotation axis must not be zero, got {}", v["axis"]))?;
+            let angle = deg_2_rad(safe_value_to_f64(&v["angle"], 0.0));
+            DMat4::from_axis_angle(axis, angle)
+        },
+        "scale" => match v.as_f64() {
+            Some(s) => DMat4::from_scale(DVec3::splat(s)),
+            None => DMat4::from_scale(vec3_from_json(v, DVec3::ONE))
+        },
+        "matrix" => {
+            let values: Vec<f64> = match v.as_array() {
+                Some(x) if x.len() == 16 => x.iter().map(|e| safe_value_to_f64(e, 0.0)).collect(),
+                _ => return Err(format!("transform matrix needs 16 values, got {}", v))
+            };
+            // values are written row by row, glam stores columns
+            DMat4::from_cols_slice(&values).transpose()
+        },
+        "lookat" => {
+            let from = vec3_from_json(&v["from"], DVec3::ZERO);
+            let at = vec3_from_json(&v["at"], DVec3::NEG_Z);
+            let up = vec3_from_json(&v["up"], DVec3::Y);
+            if (at - from).try_normalize().is_none() {
+                return Err(format!("lookat \"from\" and \"at\" must differ, got {}", v));
+            }
+            if (at - from).cross(up).try_normalize().is_none() {
+                return Err(format!("lookat \"up\" must not be parallel to the view direction, got {}", v));
+            }
+            // local -z axis points from "from" towards "at", like the camera
+            DMat4::look_at_rh(from, at, up).inverse()
+        },
+        _ => return Err(format!("unknown transform operation \"{}\"", name))
+    };
+    Ok(m)
+}
+
+// rotation about "axis" by "angle" times pi followed by translation to "o"
+fn legacy_from_json(j: &Value) -> DMat4 {
+    let translation_vec = vec3_from_json(&j["o"], DVec3::ZERO);
+    let axis_vec = vec3_from_json(&j["axis"], DVec3::ZERO);
+
+    let angle = match j.get("angle") {
+        Some(v) => M_PI * safe_value_to_f64(v, 0.0),
+        None => 0.0
+    };
+
+    DMat4::from_rotation_translation(
+        DQuat::from_axis_angle(axis_vec, angle), 
+        translation_vec)
+}